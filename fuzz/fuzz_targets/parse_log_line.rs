@@ -0,0 +1,16 @@
+//! Feeds arbitrary bytes through the trace line parser, which has to reject them without
+//! panicking. Run with `cargo +nightly fuzz run parse_log_line`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use log_viewer::parsing::{ParseConfig, extract_meter_values, parse_log_line};
+
+fuzz_target!(|data: &[u8]| {
+    // Trace files that aren't UTF-8 are rejected before their lines are parsed.
+    if let Ok(line) = std::str::from_utf8(data) {
+        if let Some(parsed) = parse_log_line(line, &ParseConfig::default()) {
+            let _ = extract_meter_values(&parsed);
+        }
+    }
+});
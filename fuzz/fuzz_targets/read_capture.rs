@@ -0,0 +1,11 @@
+//! Feeds arbitrary bytes through the packet capture reader, which has to reject them or read
+//! what it can without panicking. Run with `cargo +nightly fuzz run read_capture`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use log_viewer::pcap::{DEFAULT_PORTS, read_capture};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = read_capture(data, &DEFAULT_PORTS);
+});
@@ -0,0 +1,294 @@
+//! Blueprint with the tabs and views of the stations and their meter values.
+
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rerun::{
+    blueprint::{
+        Blueprint, ContainerLike, Grid, Tabs, TextDocumentView, TimePanel, TimeSeriesView, Vertical,
+    },
+    external::re_sdk_types::blueprint::components::{LoopMode, PanelState, PlayState},
+};
+
+use log_viewer::ocpp::{MeterValuesBatch, OcppMessage, TraceFileEntry};
+
+use crate::plot::{PathLayout, entity_path};
+
+pub fn time_series_view(name: &str, root: &str, contents: &[&str]) -> ContainerLike {
+    ContainerLike::from(
+        TimeSeriesView::new(name).with_origin("/").with_contents(
+            contents
+                .iter()
+                .map(|content| entity_path(root, content))
+                .collect::<Vec<_>>(),
+        ),
+    )
+}
+
+/// A station, connector or transaction whose meter values get their own tabs.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MeterRoot {
+    /// Only set if the layout groups by date.
+    pub date: Option<NaiveDate>,
+    /// Station ID, empty without station IDs.
+    pub station: String,
+    /// Entity root of the station-level entities.
+    pub station_path: String,
+    /// Entity root of the meter values.
+    pub path: String,
+    /// Only set if the layout puts meter values below their connector.
+    pub connector_id: Option<u32>,
+    /// Only set if the layout puts meter values below their transaction.
+    pub transaction_id: Option<String>,
+}
+
+impl MeterRoot {
+    pub fn new(layout: &PathLayout, station: &str, meter_values: &MeterValuesBatch) -> Self {
+        let connector_id = meter_values.connector_id.filter(|_| !layout.flat);
+        let transaction_id = meter_values
+            .transaction_id
+            .clone()
+            .filter(|_| !layout.flat || layout.transaction_prefix);
+
+        Self {
+            date: layout.date,
+            station: station.to_owned(),
+            station_path: layout.station(station),
+            path: layout.meter_values(station, connector_id, transaction_id.as_deref()),
+            connector_id,
+            transaction_id,
+        }
+    }
+
+    /// Root of the meter values of a station that doesn't report connectors.
+    pub fn station(layout: &PathLayout, station: &str) -> Self {
+        Self {
+            date: layout.date,
+            station: station.to_owned(),
+            station_path: layout.station(station),
+            path: layout.meter_values(station, None, None),
+            connector_id: None,
+            transaction_id: None,
+        }
+    }
+
+    fn tab_name(&self, name: &str) -> String {
+        let mut parts = Vec::new();
+        if let Some(date) = self.date {
+            parts.push(date.to_string());
+        }
+        if !self.station.is_empty() {
+            parts.push(self.station.clone());
+        }
+        if let Some(connector_id) = self.connector_id {
+            parts.push(format!("connector {}", connector_id));
+        }
+        if let Some(transaction_id) = &self.transaction_id {
+            parts.push(format!("transaction {}", transaction_id));
+        }
+
+        if parts.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}: {}", parts.join(" "), name)
+        }
+    }
+}
+
+/// Tabs with the meter value views of one station or connector.
+pub fn meter_value_tabs(meter_root: &MeterRoot) -> Vec<ContainerLike> {
+    let root = &meter_root.path;
+
+    vec![
+        ContainerLike::from(
+            Vertical::new(vec![
+                ContainerLike::from(Grid::new(vec![
+                    time_series_view("Current", root, &["current/**"]),
+                    time_series_view("Power", root, &["power/**"]),
+                    time_series_view("Power factor", root, &["power_factor/**"]),
+                ])),
+                time_series_view("Voltage", root, &["voltage/**"]),
+            ])
+            .with_name(meter_root.tab_name("Meter values")),
+        ),
+        ContainerLike::from(
+            Vertical::new(vec![
+                time_series_view(
+                    "Energy",
+                    "",
+                    &[
+                        &entity_path(root, "energy/**"),
+                        &entity_path(&meter_root.station_path, "transaction/**"),
+                    ],
+                ),
+                ContainerLike::from(Grid::new(vec![
+                    time_series_view("SoC", root, &["battery/**"]),
+                    time_series_view("Temperature", root, &["temperature/**"]),
+                    time_series_view("Frequency", root, &["grid/**"]),
+                    time_series_view("RPM", root, &["cooling/**"]),
+                    time_series_view("Parse errors", root, &["parse_errors/**"]),
+                    time_series_view("Diagnostics", root, &["diagnostics/**"]),
+                ])),
+            ])
+            .with_name(meter_root.tab_name("Energy")),
+        ),
+    ]
+}
+
+/// Tab with the station-level views of one station, or of all data without station IDs.
+pub fn station_tab(station: &MeterRoot) -> ContainerLike {
+    let station_path = &station.station_path;
+
+    ContainerLike::from(
+        Grid::new(vec![
+            time_series_view("Connector status", station_path, &["connector/**"]),
+            time_series_view("Heartbeat", station_path, &["heartbeat/**"]),
+            time_series_view("Latency", station_path, &["latency/**"]),
+            time_series_view("DataTransfer", station_path, &["datatransfer/**"]),
+            time_series_view("Call errors", station_path, &["ocpp/error_count/**"]),
+            time_series_view("Acknowledgments", station_path, &["ocpp/ack/**"]),
+            time_series_view("Configuration changes", station_path, &["config/changes"]),
+            time_series_view("Resets", station_path, &["ocpp/commands/reset/**"]),
+            time_series_view("Firmware", station_path, &["firmware/status_code"]),
+            time_series_view(
+                "Diagnostics upload",
+                station_path,
+                &["diagnostics/status_code"],
+            ),
+            ContainerLike::from(
+                TextDocumentView::new("Station info")
+                    .with_origin("/")
+                    .with_contents([entity_path(station_path, "info")]),
+            ),
+        ])
+        .with_name(station.tab_name("Station")),
+    )
+}
+
+/// Tab with one view per quantity of one station or connector, see [`overview_blueprint`].
+fn overview_tab(meter_root: &MeterRoot) -> ContainerLike {
+    let root = &meter_root.path;
+    let station_path = &meter_root.station_path;
+
+    ContainerLike::from(
+        Grid::new(vec![
+            time_series_view("Voltage per phase", root, &["voltage/**"]),
+            time_series_view("Current per phase", root, &["current/**"]),
+            time_series_view(
+                "Power",
+                root,
+                &["power/active/**", "power/reactive/**", "power/apparent/**"],
+            ),
+            time_series_view("Energy", root, &["energy/**"]),
+            // The alerts are logged as text, their 0/1 series are below `diagnostics`.
+            time_series_view(
+                "Status and alerts",
+                "",
+                &[
+                    &entity_path(station_path, "connector/**"),
+                    &entity_path(root, "diagnostics/**"),
+                ],
+            ),
+        ])
+        .with_name(meter_root.tab_name("Overview")),
+    )
+}
+
+fn time_panel() -> TimePanel {
+    TimePanel::new()
+        .with_state(PanelState::Collapsed)
+        .with_timeline("time")
+        .with_loop_mode(LoopMode::Selection)
+        .with_play_state(PlayState::Following)
+}
+
+/// Blueprint of `--blueprint-output`, with an overview tab of voltage, current, power, energy and
+/// the status and alerts per meter root, or per station without meter roots.
+pub fn overview_blueprint(
+    layout: &PathLayout,
+    stations: &[MeterRoot],
+    meter_roots: &[MeterRoot],
+) -> Blueprint {
+    let roots = match (stations, meter_roots) {
+        ([], []) => vec![MeterRoot::station(layout, "")],
+        (stations, []) => stations.to_vec(),
+        (_, meter_roots) => meter_roots.to_vec(),
+    };
+
+    Blueprint::new(Tabs::new(
+        roots.iter().map(overview_tab).collect::<Vec<_>>(),
+    ))
+    .with_time_panel(time_panel())
+}
+
+/// Sets up the station tab of every station followed by the meter value tabs of its meter roots.
+/// Without stations, there is a single unprefixed set, and without meter roots, every station
+/// gets meter value tabs for the station itself.
+pub fn setup_blueprint(
+    layout: &PathLayout,
+    stations: &[MeterRoot],
+    meter_roots: &[MeterRoot],
+) -> Blueprint {
+    let stations = if stations.is_empty() {
+        vec![MeterRoot::station(layout, "")]
+    } else {
+        stations.to_vec()
+    };
+
+    let mut tabs: Vec<ContainerLike> = Vec::new();
+    for station in &stations {
+        tabs.push(station_tab(station));
+        if meter_roots.is_empty() {
+            tabs.extend(meter_value_tabs(station));
+        } else {
+            tabs.extend(
+                meter_roots
+                    .iter()
+                    .filter(|meter_root| {
+                        meter_root.date == station.date && meter_root.station == station.station
+                    })
+                    .flat_map(meter_value_tabs),
+            );
+        }
+    }
+    tabs.push(ContainerLike::from(Grid::new(vec![time_series_view(
+        "Log",
+        &layout.station(""),
+        &["log/**"],
+    )])));
+
+    Blueprint::new(Grid::new(vec![ContainerLike::from(Tabs::new(tabs))]))
+        .with_time_panel(time_panel())
+}
+
+/// Stations and meter value roots of the entries, which the blueprint has tabs for.
+pub fn blueprint_roots(
+    layout: &PathLayout,
+    trace_file_entries: &[TraceFileEntry],
+) -> (Vec<MeterRoot>, Vec<MeterRoot>) {
+    let stations = trace_file_entries
+        .iter()
+        .map(|trace_file_entry| {
+            MeterRoot::station(
+                &layout.on_date(trace_file_entry.timestamp.date_naive()),
+                trace_file_entry.station_id.as_deref().unwrap_or_default(),
+            )
+        })
+        .unique()
+        .sorted()
+        .collect::<Vec<_>>();
+    let meter_roots = trace_file_entries
+        .iter()
+        .filter_map(|trace_file_entry| match &trace_file_entry.message {
+            OcppMessage::MeterValues(meter_values) => Some(MeterRoot::new(
+                &layout.on_date(trace_file_entry.timestamp.date_naive()),
+                trace_file_entry.station_id.as_deref().unwrap_or_default(),
+                meter_values,
+            )),
+            _ => None,
+        })
+        .unique()
+        .sorted()
+        .collect::<Vec<_>>();
+
+    (stations, meter_roots)
+}
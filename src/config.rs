@@ -0,0 +1,189 @@
+//! Settings from a TOML file, with one key per command line argument, e.g.
+//! `trace_file_directory = "/data/logs"` or `extension = ["trace", "log"]`.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::{CommandFactory, Parser, parser::ValueSource};
+
+use crate::Args;
+
+/// Read if no `--config` is given and it exists.
+fn default_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("ocpp_log_viewer").join("config.toml"))
+}
+
+/// Parses the command line arguments, filling in the ones that aren't given from the config file.
+pub fn parse_args() -> anyhow::Result<Args> {
+    parse_args_from(std::env::args_os().collect(), default_config_path())
+}
+
+/// Like [`parse_args`], with `default_config` read if no `--config` is given and it exists.
+fn parse_args_from(
+    mut arguments: Vec<OsString>,
+    default_config: Option<PathBuf>,
+) -> anyhow::Result<Args> {
+    let command = Args::command();
+    // Only to find the config file and the arguments given on the command line, the required ones
+    // may still come from the config file.
+    let matches = command
+        .clone()
+        .ignore_errors(true)
+        .get_matches_from(&arguments);
+
+    // Arguments like `--list-measurands` can't be combined with the ones from the config file.
+    if command.get_arguments().any(|argument| {
+        argument.is_exclusive_set()
+            && matches.value_source(argument.get_id().as_str()) == Some(ValueSource::CommandLine)
+    }) {
+        return Ok(Args::parse_from(arguments));
+    }
+
+    let path = match matches.get_one::<PathBuf>("config") {
+        Some(path) => path.clone(),
+        None => match default_config.filter(|path| path.is_file()) {
+            Some(path) => path,
+            None => return Ok(Args::parse_from(arguments)),
+        },
+    };
+    let config = fs::read_to_string(&path)
+        .with_context(|| format!("Could not read config file `{}`", path.display()))?
+        .parse::<toml::Table>()
+        .with_context(|| format!("Could not parse config file `{}`", path.display()))?;
+
+    for (key, value) in config {
+        let Some((argument, long)) = command
+            .get_arguments()
+            .find(|argument| argument.get_id() == key.as_str() && key != "config")
+            .and_then(|argument| Some((argument, argument.get_long()?)))
+        else {
+            anyhow::bail!("Unknown key `{}` in config file `{}`", key, path.display());
+        };
+        // The command line wins.
+        if matches.value_source(&key) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                // Like `--json-col-last`, which defaults to true.
+                toml::Value::Boolean(value) if argument.get_action().takes_values() => {
+                    arguments.push(format!("--{}={}", long, value).into())
+                }
+                toml::Value::Boolean(true) => arguments.push(format!("--{}", long).into()),
+                toml::Value::Boolean(false) => {}
+                toml::Value::String(value) => {
+                    arguments.push(format!("--{}={}", long, value).into())
+                }
+                value => arguments.push(format!("--{}={}", long, value).into()),
+            }
+        }
+    }
+
+    Ok(Args::parse_from(arguments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    /// Parses `arguments` after the program name, with `config` as config file.
+    fn parse_with_config(config: &str, arguments: &[&str]) -> anyhow::Result<Args> {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), config).unwrap();
+
+        let mut all_arguments: Vec<OsString> = vec!["log_viewer".into(), "--config".into()];
+        all_arguments.push(file.path().into());
+        all_arguments.extend(arguments.iter().map(OsString::from));
+        parse_args_from(all_arguments, None)
+    }
+
+    #[test]
+    fn booleans() {
+        let args = parse_with_config(
+            "json_col_last = false\nquiet = true\nverbose = false",
+            &["--trace-file", "a.trace"],
+        )
+        .unwrap();
+
+        // `json_col_last` takes a value, the others are flags that are given or not.
+        assert!(!args.json_col_last);
+        assert!(args.quiet);
+        assert!(!args.verbose);
+    }
+
+    #[test]
+    fn arrays_and_numbers() {
+        let args = parse_with_config(
+            "trace_file_directory = [\"/a\", \"/b\"]\nextension = [\"trace\", \"log\"]\nmin_cols = 9",
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(args.exclusive.trace_file_directory, ["/a", "/b"]);
+        assert_eq!(args.extension, ["trace", "log"]);
+        assert_eq!(args.min_cols, Some(9));
+    }
+
+    #[test]
+    fn command_line_wins() {
+        let args = parse_with_config(
+            "extension = [\"log\"]\nmin_cols = 9\njson_col_last = false",
+            &[
+                "--trace-file",
+                "a.trace",
+                "--extension",
+                "trace",
+                "--min-cols",
+                "11",
+                "--json-col-last",
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(args.extension, ["trace"]);
+        assert_eq!(args.min_cols, Some(11));
+        assert!(args.json_col_last);
+    }
+
+    #[test]
+    fn unknown_keys() {
+        for key in ["colour = true", "config = \"other.toml\""] {
+            let Err(error) = parse_with_config(key, &["--trace-file", "a.trace"]) else {
+                panic!("`{}` was accepted", key);
+            };
+            assert!(error.to_string().starts_with("Unknown key"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn default_config_file() {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), "trace_file = \"a.trace\"").unwrap();
+        let parse = |arguments: &[&str]| {
+            parse_args_from(
+                arguments.iter().map(OsString::from).collect(),
+                Some(file.path().to_owned()),
+            )
+        };
+
+        let args = parse(&["log_viewer"]).unwrap();
+        assert_eq!(args.exclusive.trace_file, [PathBuf::from("a.trace")]);
+
+        // Arguments like `--list-measurands` can't be combined with the ones of the config file.
+        let args = parse(&["log_viewer", "--list-measurands"]).unwrap();
+        assert!(args.exclusive.list_measurands);
+        assert!(args.exclusive.trace_file.is_empty());
+    }
+}
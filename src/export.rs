@@ -0,0 +1,859 @@
+//! Exports of the parsed meter values to files, next to or instead of the Rerun recording.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use chrono::{DateTime, FixedOffset, NaiveDate, SecondsFormat, Utc};
+use parquet::arrow::ArrowWriter;
+use rerun::external::arrow::array::{
+    ArrayRef, Float64Array, Int32Array, Int64Array, RecordBatch, StringArray,
+};
+use rerun::external::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use rusqlite::Connection;
+use rusqlite::types::Value;
+use rust_ocpp::v1_6::types::{Measurand, Phase, UnitOfMeasure};
+use serde::Serialize;
+
+use log_viewer::ocpp::{MeterValuesBatch, SampledReading, ocpp_name};
+
+fn utc_timestamp(timestamp: &DateTime<FixedOffset>) -> String {
+    timestamp
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn timestamp_ns(timestamp: &DateTime<FixedOffset>) -> anyhow::Result<i64> {
+    timestamp
+        .timestamp_nanos_opt()
+        .with_context(|| format!("Timestamp {} is out of the nanosecond range", timestamp))
+}
+
+/// Writes one CSV row per sampled value.
+pub struct CsvExport {
+    writer: csv::Writer<File>,
+}
+
+impl CsvExport {
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Could not create CSV output `{}`", path.display()))?;
+        writer.write_record([
+            "timestamp_utc",
+            "station_id",
+            "connector_id",
+            "transaction_id",
+            "measurand",
+            "phase",
+            "unit",
+            "value",
+        ])?;
+
+        Ok(Self { writer })
+    }
+
+    pub fn write_reading(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        connector_id: Option<u32>,
+        transaction_id: Option<&str>,
+        reading: &SampledReading,
+    ) -> anyhow::Result<()> {
+        self.writer.write_record([
+            utc_timestamp(timestamp),
+            station_id.unwrap_or_default().to_owned(),
+            connector_id.map(|id| id.to_string()).unwrap_or_default(),
+            transaction_id.unwrap_or_default().to_owned(),
+            ocpp_name(&reading.measurand),
+            reading.phase.as_ref().map(ocpp_name).unwrap_or_default(),
+            reading.unit.as_ref().map(ocpp_name).unwrap_or_default(),
+            reading.value.to_string(),
+        ])?;
+
+        Ok(())
+    }
+
+    pub fn write_meter_values(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        meter_values: &MeterValuesBatch,
+    ) -> anyhow::Result<()> {
+        for reading in &meter_values.readings {
+            self.write_reading(
+                timestamp,
+                station_id,
+                meter_values.connector_id,
+                meter_values.transaction_id.as_deref(),
+                reading,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct JsonlSampledValue<'a> {
+    measurand: &'a Measurand,
+    phase: Option<&'a Phase>,
+    unit: Option<&'a UnitOfMeasure>,
+    value: f64,
+}
+
+#[derive(Serialize)]
+struct JsonlMeterValues<'a> {
+    timestamp: String,
+    station_id: Option<&'a str>,
+    connector_id: Option<u32>,
+    transaction_id: Option<&'a str>,
+    sampled_values: Vec<JsonlSampledValue<'a>>,
+}
+
+/// Appends one JSON object per MeterValues message, so repeated runs extend the same file.
+pub struct JsonlExport {
+    writer: BufWriter<File>,
+}
+
+impl JsonlExport {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Could not open JSONL output `{}`", path.display()))?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn write_meter_values(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        meter_values: &MeterValuesBatch,
+    ) -> anyhow::Result<()> {
+        serde_json::to_writer(
+            &mut self.writer,
+            &JsonlMeterValues {
+                timestamp: utc_timestamp(timestamp),
+                station_id,
+                connector_id: meter_values.connector_id,
+                transaction_id: meter_values.transaction_id.as_deref(),
+                sampled_values: meter_values
+                    .readings
+                    .iter()
+                    .map(|reading| JsonlSampledValue {
+                        measurand: &reading.measurand,
+                        phase: reading.phase.as_ref(),
+                        unit: reading.unit.as_ref(),
+                        value: reading.value,
+                    })
+                    .collect(),
+            },
+        )?;
+        self.writer.write_all(b"\n")?;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Escapes the characters InfluxDB line protocol treats specially in tag keys and values.
+fn influx_tag_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+/// Writes one InfluxDB line protocol point per sampled value, e.g. `ocpp_meter,station=EVSE001,
+/// measurand=Voltage,phase=L1 value=231.5 1705300800000000000`, for importing with the influx
+/// CLI.
+pub struct InfluxExport {
+    writer: BufWriter<File>,
+}
+
+impl InfluxExport {
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Could not create InfluxDB output `{}`", path.display()))?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn write_reading(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        reading: &SampledReading,
+    ) -> anyhow::Result<()> {
+        let timestamp_ns = timestamp_ns(timestamp)?;
+
+        let mut tags = String::new();
+        // Empty tag values aren't allowed, so missing ones are left out.
+        if let Some(station_id) = station_id.filter(|station_id| !station_id.is_empty()) {
+            tags.push_str(&format!(",station={}", influx_tag_value(station_id)));
+        }
+        tags.push_str(&format!(
+            ",measurand={}",
+            influx_tag_value(&ocpp_name(&reading.measurand))
+        ));
+        if let Some(phase) = &reading.phase {
+            tags.push_str(&format!(",phase={}", influx_tag_value(&ocpp_name(phase))));
+        }
+
+        writeln!(
+            self.writer,
+            "ocpp_meter{} value={} {}",
+            tags, reading.value, timestamp_ns
+        )?;
+
+        Ok(())
+    }
+
+    pub fn write_meter_values(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        meter_values: &MeterValuesBatch,
+    ) -> anyhow::Result<()> {
+        for reading in &meter_values.readings {
+            self.write_reading(timestamp, station_id, reading)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Rows are written to the Parquet files in row groups of this many rows.
+const PARQUET_BATCH_ROWS: usize = 65_536;
+
+fn parquet_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp_ns", DataType::Int64, false),
+        Field::new("station_id", DataType::Utf8, false),
+        Field::new("connector_id", DataType::Int32, true),
+        Field::new("transaction_id", DataType::Int64, true),
+        Field::new("transaction_id_raw", DataType::Utf8, true),
+        Field::new("measurand", DataType::Utf8, false),
+        Field::new("phase", DataType::Utf8, true),
+        Field::new("unit", DataType::Utf8, false),
+        Field::new("value", DataType::Float64, false),
+    ]))
+}
+
+/// Columns of the rows that weren't written to a Parquet file yet.
+#[derive(Default)]
+struct ParquetRows {
+    timestamp_ns: Vec<i64>,
+    station_id: Vec<String>,
+    connector_id: Vec<Option<i32>>,
+    transaction_id: Vec<Option<i64>>,
+    transaction_id_raw: Vec<Option<String>>,
+    measurand: Vec<String>,
+    phase: Vec<Option<String>>,
+    unit: Vec<String>,
+    value: Vec<f64>,
+}
+
+impl ParquetRows {
+    fn take_batch(&mut self, schema: SchemaRef) -> anyhow::Result<RecordBatch> {
+        let rows = std::mem::take(self);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from(rows.timestamp_ns)),
+            Arc::new(StringArray::from(rows.station_id)),
+            Arc::new(Int32Array::from(rows.connector_id)),
+            Arc::new(Int64Array::from(rows.transaction_id)),
+            Arc::new(StringArray::from(rows.transaction_id_raw)),
+            Arc::new(StringArray::from(rows.measurand)),
+            Arc::new(StringArray::from(rows.phase)),
+            Arc::new(StringArray::from(rows.unit)),
+            Arc::new(Float64Array::from(rows.value)),
+        ];
+
+        Ok(RecordBatch::try_new(schema, columns)?)
+    }
+}
+
+/// A Parquet file and the rows buffered for its next row group.
+struct ParquetFile {
+    writer: ArrowWriter<File>,
+    rows: ParquetRows,
+}
+
+impl ParquetFile {
+    fn create(path: &Path) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Could not create Parquet output `{}`", path.display()))?;
+
+        Ok(Self {
+            writer: ArrowWriter::try_new(file, parquet_schema(), None)?,
+            rows: ParquetRows::default(),
+        })
+    }
+
+    fn write_rows(&mut self) -> anyhow::Result<()> {
+        let batch = self.rows.take_batch(parquet_schema())?;
+        self.writer.write(&batch)?;
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> anyhow::Result<()> {
+        self.write_rows()?;
+        self.writer.close()?;
+
+        Ok(())
+    }
+}
+
+/// Writes one Parquet row per sampled value, to a single file or to one file per UTC date in
+/// Hive-style `date=YYYY-MM-DD` directories. Transaction IDs are also written as they were reported
+/// to `transaction_id_raw`, for the ones that aren't integers, like the ones of OCPP 2.0.1. Like in
+/// the CSV export, the unit is an empty string if none was reported.
+pub struct ParquetExport {
+    path: PathBuf,
+    partition_by_date: bool,
+    /// Keyed by the date of the partition, or by `None` without partitions.
+    files: BTreeMap<Option<NaiveDate>, ParquetFile>,
+}
+
+impl ParquetExport {
+    /// Creates the file, or the directory of the partitions with `partition_by_date`.
+    pub fn create(path: &Path, partition_by_date: bool) -> anyhow::Result<Self> {
+        let mut files = BTreeMap::new();
+        if partition_by_date {
+            fs::create_dir_all(path).with_context(|| {
+                format!(
+                    "Could not create Parquet output directory `{}`",
+                    path.display()
+                )
+            })?;
+        } else {
+            files.insert(None, ParquetFile::create(path)?);
+        }
+
+        Ok(Self {
+            path: path.to_owned(),
+            partition_by_date,
+            files,
+        })
+    }
+
+    fn file(&mut self, timestamp: &DateTime<FixedOffset>) -> anyhow::Result<&mut ParquetFile> {
+        let date = self
+            .partition_by_date
+            .then(|| timestamp.with_timezone(&Utc).date_naive());
+        if !self.files.contains_key(&date) {
+            let directory = self.path.join(format!(
+                "date={}",
+                date.expect("The unpartitioned file is created up front")
+            ));
+            fs::create_dir_all(&directory).with_context(|| {
+                format!(
+                    "Could not create Parquet partition `{}`",
+                    directory.display()
+                )
+            })?;
+            self.files
+                .insert(date, ParquetFile::create(&directory.join("data.parquet"))?);
+        }
+
+        Ok(self
+            .files
+            .get_mut(&date)
+            .expect("The file was inserted above"))
+    }
+
+    pub fn write_reading(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        connector_id: Option<u32>,
+        transaction_id: Option<&str>,
+        reading: &SampledReading,
+    ) -> anyhow::Result<()> {
+        let timestamp_ns = timestamp_ns(timestamp)?;
+        let file = self.file(timestamp)?;
+
+        let rows = &mut file.rows;
+        rows.timestamp_ns.push(timestamp_ns);
+        rows.station_id
+            .push(station_id.unwrap_or_default().to_owned());
+        rows.connector_id
+            .push(connector_id.and_then(|id| i32::try_from(id).ok()));
+        rows.transaction_id
+            .push(transaction_id.and_then(|id| id.parse().ok()));
+        rows.transaction_id_raw
+            .push(transaction_id.map(str::to_owned));
+        rows.measurand.push(ocpp_name(&reading.measurand));
+        rows.phase.push(reading.phase.as_ref().map(ocpp_name));
+        rows.unit
+            .push(reading.unit.as_ref().map(ocpp_name).unwrap_or_default());
+        rows.value.push(reading.value);
+
+        if rows.value.len() >= PARQUET_BATCH_ROWS {
+            file.write_rows()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_meter_values(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        meter_values: &MeterValuesBatch,
+    ) -> anyhow::Result<()> {
+        for reading in &meter_values.readings {
+            self.write_reading(
+                timestamp,
+                station_id,
+                meter_values.connector_id,
+                meter_values.transaction_id.as_deref(),
+                reading,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        for file in self.files.into_values() {
+            file.finish()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Inserts one row per sampled value into the `meter_values` table of a SQLite database, which is
+/// created if needed. All rows of a run are inserted in one transaction.
+pub struct SqliteExport {
+    connection: Connection,
+}
+
+impl SqliteExport {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Could not open SQLite output `{}`", path.display()))?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS meter_values (
+                    id INTEGER PRIMARY KEY,
+                    timestamp_utc TEXT NOT NULL,
+                    station_id TEXT,
+                    connector_id INTEGER,
+                    transaction_id INTEGER,
+                    measurand TEXT NOT NULL,
+                    phase TEXT,
+                    unit TEXT,
+                    value REAL NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS meter_values_station_id_timestamp_utc
+                    ON meter_values (station_id, timestamp_utc);
+                BEGIN;",
+            )
+            .with_context(|| format!("Could not set up SQLite output `{}`", path.display()))?;
+
+        Ok(Self { connection })
+    }
+
+    pub fn write_reading(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        connector_id: Option<u32>,
+        transaction_id: Option<&str>,
+        reading: &SampledReading,
+    ) -> anyhow::Result<()> {
+        // The column affinity keeps the IDs that aren't integers, like the ones of OCPP 2.0.1, as
+        // text.
+        let transaction_id = transaction_id.map(|id| match id.parse() {
+            Ok(id) => Value::Integer(id),
+            Err(_) => Value::Text(id.to_owned()),
+        });
+
+        self.connection
+            .prepare_cached(
+                "INSERT INTO meter_values (timestamp_utc, station_id, connector_id, \
+                 transaction_id, measurand, phase, unit, value) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?
+            .execute((
+                // With a fixed number of digits, so that the text sorts by time.
+                timestamp
+                    .with_timezone(&Utc)
+                    .to_rfc3339_opts(SecondsFormat::Millis, true),
+                station_id,
+                connector_id,
+                transaction_id,
+                ocpp_name(&reading.measurand),
+                reading.phase.as_ref().map(ocpp_name),
+                reading.unit.as_ref().map(ocpp_name),
+                reading.value,
+            ))?;
+
+        Ok(())
+    }
+
+    pub fn write_meter_values(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        meter_values: &MeterValuesBatch,
+    ) -> anyhow::Result<()> {
+        for reading in &meter_values.readings {
+            self.write_reading(
+                timestamp,
+                station_id,
+                meter_values.connector_id,
+                meter_values.transaction_id.as_deref(),
+                reading,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        self.connection.execute_batch("COMMIT;")?;
+
+        Ok(())
+    }
+}
+
+/// The export files given on the command line, each of them optional.
+#[derive(Default)]
+pub struct Exports {
+    pub csv: Option<CsvExport>,
+    pub jsonl: Option<JsonlExport>,
+    pub influx: Option<InfluxExport>,
+    pub parquet: Option<ParquetExport>,
+    pub sqlite: Option<SqliteExport>,
+}
+
+impl Exports {
+    /// Writes a value that didn't come with a MeterValues message, to the exports with one record
+    /// per value.
+    pub fn write_reading(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        reading: &SampledReading,
+    ) -> anyhow::Result<()> {
+        if let Some(csv) = &mut self.csv {
+            csv.write_reading(timestamp, None, None, None, reading)?;
+        }
+        if let Some(influx) = &mut self.influx {
+            influx.write_reading(timestamp, None, reading)?;
+        }
+        if let Some(parquet) = &mut self.parquet {
+            parquet.write_reading(timestamp, None, None, None, reading)?;
+        }
+        if let Some(sqlite) = &mut self.sqlite {
+            sqlite.write_reading(timestamp, None, None, None, reading)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_meter_values(
+        &mut self,
+        timestamp: &DateTime<FixedOffset>,
+        station_id: Option<&str>,
+        meter_values: &MeterValuesBatch,
+    ) -> anyhow::Result<()> {
+        if let Some(csv) = &mut self.csv {
+            csv.write_meter_values(timestamp, station_id, meter_values)?;
+        }
+        if let Some(jsonl) = &mut self.jsonl {
+            jsonl.write_meter_values(timestamp, station_id, meter_values)?;
+        }
+        if let Some(influx) = &mut self.influx {
+            influx.write_meter_values(timestamp, station_id, meter_values)?;
+        }
+        if let Some(parquet) = &mut self.parquet {
+            parquet.write_meter_values(timestamp, station_id, meter_values)?;
+        }
+        if let Some(sqlite) = &mut self.sqlite {
+            sqlite.write_meter_values(timestamp, station_id, meter_values)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        if let Some(csv) = self.csv {
+            csv.finish()?;
+        }
+        if let Some(jsonl) = self.jsonl {
+            jsonl.finish()?;
+        }
+        if let Some(influx) = self.influx {
+            influx.finish()?;
+        }
+        if let Some(parquet) = self.parquet {
+            parquet.finish()?;
+        }
+        if let Some(sqlite) = self.sqlite {
+            sqlite.finish()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use rerun::external::arrow::array::Array;
+    use tempfile::TempDir;
+
+    fn utc(date_time: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(date_time).unwrap()
+    }
+
+    fn voltage(unit: Option<UnitOfMeasure>) -> SampledReading {
+        SampledReading {
+            measurand: Measurand::Voltage,
+            phase: Some(Phase::L1),
+            unit,
+            value: 230.5,
+        }
+    }
+
+    #[test]
+    fn csv_fields_are_quoted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("meter_values.csv");
+        let mut csv = CsvExport::create(&path).unwrap();
+        csv.write_reading(
+            &utc("2024-01-15T08:00:00Z"),
+            Some("EVSE,\"1\""),
+            Some(1),
+            Some("7"),
+            &voltage(None),
+        )
+        .unwrap();
+        csv.finish().unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(r#"2024-01-15T08:00:00Z,"EVSE,""1""",1,7,Voltage,L1,,230.5"#));
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(&record[1], "EVSE,\"1\"");
+    }
+
+    #[test]
+    fn jsonl_is_appended_to() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("meter_values.jsonl");
+        let meter_values = MeterValuesBatch {
+            connector_id: Some(1),
+            transaction_id: Some("7".to_owned()),
+            readings: vec![voltage(Some(UnitOfMeasure::V))],
+            invalid_readings: Vec::new(),
+        };
+        for timestamp in ["2024-01-15T08:00:00Z", "2024-01-15T08:01:00Z"] {
+            let mut jsonl = JsonlExport::open(&path).unwrap();
+            jsonl
+                .write_meter_values(&utc(timestamp), Some("EVSE-001"), &meter_values)
+                .unwrap();
+            jsonl.finish().unwrap();
+        }
+
+        let lines: Vec<serde_json::Value> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["timestamp"], "2024-01-15T08:00:00Z");
+        assert_eq!(lines[1]["timestamp"], "2024-01-15T08:01:00Z");
+        assert_eq!(lines[1]["sampled_values"][0]["unit"], "V");
+    }
+
+    #[test]
+    fn influx_tags_are_escaped() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("meter_values.lp");
+        let mut influx = InfluxExport::create(&path).unwrap();
+        influx
+            .write_reading(
+                &utc("2024-01-15T08:00:00.123456789+01:00"),
+                Some(r"EVSE 1,a=b\c"),
+                &voltage(None),
+            )
+            .unwrap();
+        // Without the empty station tag.
+        influx
+            .write_reading(&utc("1970-01-01T00:00:00Z"), Some(""), &voltage(None))
+            .unwrap();
+        influx.finish().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "ocpp_meter,station=EVSE\\ 1\\,a\\=b\\\\c,measurand=Voltage,phase=L1 value=230.5 \
+             1705302000123456789\n\
+             ocpp_meter,measurand=Voltage,phase=L1 value=230.5 0\n"
+        );
+    }
+
+    #[test]
+    fn parquet_partitions_by_date() {
+        let dir = TempDir::new().unwrap();
+        let mut parquet = ParquetExport::create(dir.path(), true).unwrap();
+        parquet
+            .write_reading(
+                &utc("2024-01-15T23:59:59+00:00"),
+                Some("EVSE-001"),
+                Some(1),
+                Some("7"),
+                &voltage(Some(UnitOfMeasure::V)),
+            )
+            .unwrap();
+        // The date is the UTC one.
+        parquet
+            .write_reading(
+                &utc("2024-01-16T00:30:00+01:00"),
+                None,
+                None,
+                Some("a1b2-c3"),
+                &voltage(None),
+            )
+            .unwrap();
+        parquet
+            .write_reading(
+                &utc("2024-01-16T08:00:00Z"),
+                None,
+                None,
+                None,
+                &voltage(None),
+            )
+            .unwrap();
+        parquet.finish().unwrap();
+
+        let read = |date: &str| {
+            let file = File::open(dir.path().join(date).join("data.parquet")).unwrap();
+            let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+                .unwrap()
+                .build()
+                .unwrap();
+            reader.map(Result::unwrap).collect::<Vec<_>>()
+        };
+        let batches = read("date=2024-01-15");
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 2);
+        let schema = batches[0].schema();
+        assert_eq!(schema, parquet_schema());
+        assert!(
+            !schema
+                .field_with_name("timestamp_ns")
+                .unwrap()
+                .is_nullable()
+        );
+        assert!(
+            schema
+                .field_with_name("transaction_id")
+                .unwrap()
+                .is_nullable()
+        );
+        assert!(!schema.field_with_name("unit").unwrap().is_nullable());
+
+        let column = |name: &str| batches[0].column_by_name(name).unwrap().clone();
+        let transaction_ids = column("transaction_id");
+        let transaction_ids = transaction_ids
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(transaction_ids.value(0), 7);
+        assert!(transaction_ids.is_null(1));
+        let raw_ids = column("transaction_id_raw");
+        let raw_ids = raw_ids.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(raw_ids.value(1), "a1b2-c3");
+        let units = column("unit");
+        let units = units.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(units.value(0), "V");
+        assert_eq!(units.value(1), "");
+
+        assert_eq!(read("date=2024-01-16")[0].num_rows(), 1);
+    }
+
+    #[test]
+    fn sqlite_is_appended_to() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("meter_values.db");
+        for transaction_id in ["7", "a1b2-c3"] {
+            let mut sqlite = SqliteExport::open(&path).unwrap();
+            sqlite
+                .write_reading(
+                    &utc("2024-01-15T08:00:00Z"),
+                    Some("EVSE-001"),
+                    Some(1),
+                    Some(transaction_id),
+                    &voltage(None),
+                )
+                .unwrap();
+            sqlite.finish().unwrap();
+        }
+
+        let connection = Connection::open(&path).unwrap();
+        let rows: Vec<(String, Value, Option<String>)> = connection
+            .prepare("SELECT timestamp_utc, transaction_id, unit FROM meter_values ORDER BY id")
+            .unwrap()
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "2024-01-15T08:00:00.000Z".to_owned(),
+                    Value::Integer(7),
+                    None
+                ),
+                (
+                    "2024-01-15T08:00:00.000Z".to_owned(),
+                    Value::Text("a1b2-c3".to_owned()),
+                    None
+                ),
+            ]
+        );
+        let index: String = connection
+            .query_row(
+                "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'meter_values'",
+                (),
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(index, "meter_values_station_id_timestamp_utc");
+    }
+}
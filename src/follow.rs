@@ -0,0 +1,132 @@
+//! Watching trace files for appended lines after the existing ones were processed, `tail -f`
+//! style.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use notify::{EventKind, RecursiveMode, Watcher};
+use rerun::external::re_log;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::mpsc;
+
+use log_viewer::parsing::strip_bom;
+
+use crate::{COMPRESSED_EXTENSIONS, has_file_extension};
+
+/// Byte offset up to which each trace file was processed.
+#[derive(Default)]
+pub struct FileOffsets {
+    offsets: HashMap<PathBuf, u64>,
+}
+
+impl FileOffsets {
+    /// Marks the current content of the file as processed.
+    pub async fn mark_read(&mut self, path: &Path) -> io::Result<()> {
+        let length = tokio::fs::metadata(path).await?.len();
+        self.offsets.insert(key(path), length);
+
+        Ok(())
+    }
+
+    /// Reads the complete lines appended since the last call. A trailing line without newline is
+    /// left in the file until it is completed. A file that shrank is read from the start again.
+    pub async fn read_appended_lines(&mut self, path: &Path) -> io::Result<Vec<String>> {
+        let mut file = File::open(path).await?;
+        let length = file.metadata().await?.len();
+        let offset = self.offsets.entry(key(path)).or_default();
+        if length < *offset {
+            *offset = 0;
+        }
+
+        file.seek(SeekFrom::Start(*offset)).await?;
+        let mut appended = Vec::new();
+        file.take(length - *offset)
+            .read_to_end(&mut appended)
+            .await?;
+        let Some(end) = appended.iter().rposition(|byte| *byte == b'\n') else {
+            return Ok(Vec::new());
+        };
+        *offset += end as u64 + 1;
+
+        Ok(strip_bom(&String::from_utf8_lossy(&appended[..end]))
+            .split("\n")
+            .map(|line| line.to_owned())
+            .collect())
+    }
+}
+
+fn key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+/// Watches the directories recursively and the files for created or modified trace files with one
+/// of `extensions` and passes their appended lines to `on_lines`, until Ctrl-C is pressed.
+/// Compressed files can't be appended to and are ignored.
+pub async fn follow(
+    directories: &[String],
+    files: &[PathBuf],
+    extensions: &[String],
+    offsets: &mut FileOffsets,
+    mut on_lines: impl FnMut(Vec<String>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        // The receiver is only dropped once following stopped.
+        let _ = sender.send(event);
+    })?;
+    for directory in directories {
+        watcher
+            .watch(Path::new(directory), RecursiveMode::Recursive)
+            .with_context(|| format!("Could not watch `{}`", directory))?;
+    }
+    for file in files {
+        watcher
+            .watch(file, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Could not watch `{}`", file.display()))?;
+    }
+
+    let is_trace_file = |path: &Path| {
+        !COMPRESSED_EXTENSIONS
+            .iter()
+            .any(|compressed| path.extension().is_some_and(|ext| ext == *compressed))
+            && extensions
+                .iter()
+                .any(|ext| ext == "*" || has_file_extension(path, ext))
+    };
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        let event = tokio::select! {
+            result = &mut ctrl_c => {
+                result.context("Could not listen for Ctrl-C")?;
+                break;
+            }
+            event = receiver.recv() => match event {
+                Some(event) => event?,
+                None => break,
+            },
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            continue;
+        }
+
+        for path in event.paths.iter().filter(|path| path.is_file()) {
+            if !is_trace_file(path) {
+                continue;
+            }
+
+            match offsets.read_appended_lines(path).await {
+                Ok(lines) if lines.is_empty() => {}
+                Ok(lines) => on_lines(lines)?,
+                Err(e) => re_log::warn!("Could not read `{}`: {}", path.display(), e),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Standalone HTML report of the traces, for sharing without a Rerun viewer: the session table,
+//! a chart per measurand and connector and the status transitions of the connectors.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use chrono_tz::Tz;
+use serde_json::json;
+
+use log_viewer::ocpp::{MEASURANDS, OcppMessage, TraceFileEntry, measurand_description, ocpp_name};
+
+use crate::report::{COLUMNS, ReportFormat, SessionReport};
+
+/// Loaded by the browser when opening the report, everything else is inlined.
+const CHART_JS_URL: &str = "https://cdn.jsdelivr.net/npm/chart.js@4.4.1/dist/chart.umd.min.js";
+
+/// Renders the charts of the `charts` array that the report defines before this script.
+const CHARTS_SCRIPT: &str = r#"
+for (const chart of charts) {
+  const section = document.createElement("section");
+  const heading = document.createElement("h3");
+  heading.textContent = chart.title;
+  const canvas = document.createElement("canvas");
+  section.append(heading, canvas);
+  document.getElementById("charts").append(section);
+
+  new Chart(canvas, {
+    type: "line",
+    data: { datasets: chart.datasets },
+    options: {
+      animation: false,
+      parsing: false,
+      elements: { point: { radius: 0 } },
+      scales: {
+        x: {
+          type: "linear",
+          ticks: { callback: (value) => new Date(value).toISOString().replace(".000Z", "Z") },
+        },
+        y: { title: { display: chart.unit !== "", text: chart.unit } },
+      },
+    },
+  });
+}
+"#;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+section { max-width: 60em; margin-bottom: 2em; }
+";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) -> String {
+    let row = |cells: Vec<String>, tag: &str| {
+        let cells = cells
+            .iter()
+            .map(|cell| format!("<{tag}>{}</{tag}>", escape_html(cell)))
+            .collect::<String>();
+        format!("<tr>{}</tr>\n", cells)
+    };
+
+    format!(
+        "<table>\n{}{}</table>\n",
+        row(header.map(str::to_owned).to_vec(), "th"),
+        rows.iter()
+            .map(|cells| row(cells.to_vec(), "td"))
+            .collect::<String>()
+    )
+}
+
+/// Station ID, connector ID and index into [`MEASURANDS`] of a chart.
+type ChartKey = (String, Option<u32>, usize);
+
+/// Collects what the report shows from the plotted entries and writes it once all are processed.
+pub struct HtmlReport {
+    sessions: SessionReport,
+    /// Of the status transitions.
+    timezone: Tz,
+    /// Unix time in milliseconds and value of the sampled values per phase, `-` without one.
+    charts: BTreeMap<ChartKey, BTreeMap<String, Vec<(i64, f64)>>>,
+    /// Time, station, connector, status and error code of the StatusNotifications.
+    status_transitions: Vec<[String; 5]>,
+}
+
+impl HtmlReport {
+    pub fn new(timezone: Tz) -> Self {
+        Self {
+            sessions: SessionReport::new(ReportFormat::Table, timezone),
+            timezone,
+            charts: BTreeMap::new(),
+            status_transitions: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, trace_file_entry: &TraceFileEntry) {
+        self.sessions.add(trace_file_entry);
+
+        let station_id = trace_file_entry.station_id.clone().unwrap_or_default();
+        match &trace_file_entry.message {
+            OcppMessage::MeterValues(meter_values) => {
+                for reading in &meter_values.readings {
+                    let Some(measurand_index) = MEASURANDS
+                        .iter()
+                        .position(|measurand| *measurand == reading.measurand)
+                    else {
+                        continue;
+                    };
+
+                    self.charts
+                        .entry((
+                            station_id.clone(),
+                            meter_values.connector_id,
+                            measurand_index,
+                        ))
+                        .or_default()
+                        .entry(reading.phase.as_ref().map_or("-".to_owned(), ocpp_name))
+                        .or_default()
+                        .push((trace_file_entry.timestamp.timestamp_millis(), reading.value));
+                }
+            }
+            OcppMessage::StatusNotification(request) => {
+                self.status_transitions.push([
+                    self.time(&trace_file_entry.timestamp),
+                    station_id,
+                    request.connector_id.to_string(),
+                    ocpp_name(&request.status),
+                    ocpp_name(&request.error_code),
+                ]);
+            }
+            _ => {}
+        }
+    }
+
+    fn time(&self, timestamp: &DateTime<FixedOffset>) -> String {
+        timestamp
+            .with_timezone(&self.timezone)
+            .to_rfc3339_opts(SecondsFormat::AutoSi, false)
+    }
+
+    fn charts_json(&self) -> serde_json::Value {
+        self.charts
+            .iter()
+            .map(|((station_id, connector_id, measurand_index), phases)| {
+                let measurand = &MEASURANDS[*measurand_index];
+                let mut title = station_id.clone();
+                if let Some(connector_id) = connector_id {
+                    title.push_str(&format!(" connector {}", connector_id));
+                }
+
+                json!({
+                    "title": format!("{}: {}", title.trim_start(), ocpp_name(measurand)),
+                    "unit": measurand_description(measurand).1,
+                    "datasets": phases
+                        .iter()
+                        .map(|(phase, values)| json!({
+                            "label": phase,
+                            "data": values
+                                .iter()
+                                .map(|(x, y)| json!({"x": x, "y": y}))
+                                .collect::<Vec<_>>(),
+                        }))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        // Keeps a `</script>` in a station ID from ending the script early.
+        let charts = self.charts_json().to_string().replace("</", "<\\/");
+        let html = format!(
+            "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>OCPP trace report</title>
+<style>{STYLE}</style>
+<script src=\"{CHART_JS_URL}\"></script>
+</head>
+<body>
+<h1>OCPP trace report</h1>
+<h2>Sessions</h2>
+{}<h2>Status transitions</h2>
+{}<h2>Meter values</h2>
+<div id=\"charts\"></div>
+<script>
+const charts = {charts};
+{CHARTS_SCRIPT}</script>
+</body>
+</html>
+",
+            html_table(COLUMNS, &self.sessions.cells()),
+            html_table(
+                ["time", "station_id", "connector_id", "status", "error_code"],
+                &self.status_transitions
+            ),
+        );
+
+        fs::write(path, html)
+            .with_context(|| format!("Could not write HTML report `{}`", path.display()))
+    }
+}
@@ -0,0 +1,24 @@
+//! Parsing of OCPP trace files, packet captures and energy log files, shared by the viewer and the
+//! fuzz targets.
+
+pub mod log_file;
+pub mod ocpp;
+pub mod parsing;
+pub mod pcap;
+pub mod soap;
+pub mod validation;
+
+use std::io::{self, IsTerminal};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Progress is only shown on a terminal, so piped output and CI logs stay clean.
+pub fn progress_bar(progress_bar: ProgressBar, template: &str) -> ProgressBar {
+    if !io::stdout().is_terminal() {
+        return ProgressBar::hidden();
+    }
+
+    progress_bar.with_style(
+        ProgressStyle::with_template(template).expect("Progress bar template should be valid"),
+    )
+}
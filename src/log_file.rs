@@ -0,0 +1,114 @@
+//! Parsing of the energy log files with the PV production, battery load and EV import.
+
+use chrono::DateTime;
+use regex::Regex;
+
+use crate::parsing::TimeRange;
+
+pub struct LogFileEntry {
+    pub timestamp: f64,
+    pub pv_overproduction: f64,
+    pub battery_load: f64,
+    pub ev_import: f64,
+    pub load_overall: f64,
+    pub overproduction: f64,
+}
+
+pub fn parse_log_file_entries(
+    contents: &Vec<String>,
+    time_range: &TimeRange,
+) -> anyhow::Result<Vec<LogFileEntry>> {
+    let mut log_file_entries: Vec<LogFileEntry> = Vec::new();
+
+    let re = Regex::new(r"([a-zA-Z]+) (-?[0-9]+(\.[0-9]+)?) \+ (-?[0-9]+(\.[0-9]+)?) \+ (-?[0-9]+(\.[0-9]+)?) \+ (-?[0-9]+(\.[0-9]+)?) = (-?[0-9]+(\.[0-9]+)?)").unwrap();
+    let mut average_count = 0;
+    let mut pv_overproduction_average = 0.0;
+    let mut battery_load_average = 0.0;
+    let mut ev_import_average = 0.0;
+    let mut load_overall_average = 0.0;
+    let mut overproduction_average = 0.0;
+
+    for line in contents {
+        // Files with Windows line endings leave a `\r` at the end of every line.
+        let line = line.trim_end_matches(['\r', '\n']);
+        let line_parts = line
+            .split(char::is_whitespace)
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        if line_parts.len() < 2 {
+            continue;
+        }
+        let (date, time) = (line_parts[0].clone(), line_parts[1].clone());
+        let date_time = format!("{} {} +00:00", date.as_str().replace("[", ""), time);
+        if date_time.is_empty() {
+            continue;
+        }
+
+        let timestamp = match DateTime::parse_from_str(date_time.as_str(), "%Y-%m-%d %H:%M:%S %z") {
+            Ok(d) => d,
+            _ => continue,
+        };
+
+        if !time_range.contains(&timestamp) {
+            continue;
+        }
+
+        let Some(caps) = re.captures(line) else {
+            continue;
+        };
+
+        let pv_overproduction = caps[2].parse::<f64>()?;
+        let battery_load = caps[6].parse::<f64>()?;
+        let ev_import = caps[8].parse::<f64>()?;
+        let load_overall = (caps[4].parse::<f64>()? + caps[8].parse::<f64>()?).abs();
+        let overproduction = if caps[10].parse::<f64>()? >= 0.0 {
+            caps[10].parse::<f64>()?
+        } else {
+            0.0
+        };
+
+        if log_file_entries.is_empty() {
+            log_file_entries.push(LogFileEntry {
+                timestamp: timestamp.timestamp() as f64,
+                pv_overproduction,
+                battery_load,
+                ev_import,
+                load_overall,
+                overproduction,
+            });
+        } else {
+            let time_delta =
+                timestamp.timestamp() as f64 - log_file_entries.last().unwrap().timestamp;
+            let threshold = chrono::Duration::minutes(5).as_seconds_f64();
+
+            if time_delta >= threshold {
+                log_file_entries.push(LogFileEntry {
+                    timestamp: timestamp.timestamp() as f64,
+                    pv_overproduction: pv_overproduction_average / average_count as f64,
+                    battery_load: battery_load_average / average_count as f64,
+                    ev_import: ev_import_average / average_count as f64,
+                    load_overall: load_overall_average / average_count as f64,
+                    overproduction: overproduction_average / average_count as f64,
+                });
+
+                average_count = 0;
+                pv_overproduction_average = 0.0;
+                battery_load_average = 0.0;
+                ev_import_average = 0.0;
+                load_overall_average = 0.0;
+                overproduction_average = 0.0;
+            } else {
+                pv_overproduction_average += pv_overproduction;
+                battery_load_average += battery_load;
+                ev_import_average += ev_import;
+                load_overall_average += load_overall;
+                overproduction_average += overproduction;
+
+                average_count += 1;
+            }
+        }
+    }
+
+    Ok(log_file_entries)
+}
@@ -1,499 +1,1500 @@
-use chrono::DateTime;
-use clap::Parser;
-use glob::glob;
-use rerun::{
-    AnnotationContext, RecordingStream, Scalars,
-    blueprint::{Blueprint, ContainerLike, Grid, Tabs, TimePanel, TimeSeriesView, Vertical},
-    datatypes::ClassDescriptionMapElem,
-    external::{
-        re_log,
-        re_sdk_types::blueprint::components::{LoopMode, PanelState, PlayState},
-    },
-};
-use rust_ocpp::v1_6::types::Phase;
+mod blueprint;
+mod config;
+mod export;
+mod follow;
+mod html_report;
+mod plot;
+mod report;
+mod theme;
+
+use anyhow::Context;
+use chrono::{DateTime, FixedOffset};
+use chrono_tz::Tz;
+use flate2::read::GzDecoder;
+use glob::{Pattern, glob};
+use indicatif::ProgressBar;
+use itertools::Itertools;
+use rayon::prelude::*;
+use rerun::{RecordingStream, blueprint::BlueprintActivation, external::re_log};
+use rust_ocpp::v1_6::types::{Measurand, Phase};
+use std::collections::{BTreeMap, btree_map};
 use std::fs;
-
-use regex::Regex;
-
+use std::io::{self, BufRead, BufReader};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+
+use log_viewer::log_file::parse_log_file_entries;
+use log_viewer::ocpp::{
+    MEASURANDS, OcppMessage, PHASES, SampledReading, TraceFileEntry, measurand_description,
+    measurand_from_name, ocpp_name, parse_measurand_name, phase_description,
+};
+use log_viewer::parsing::{
+    LineFormat, MeasurandFilter, ParseConfig, TimeRange, TraceLine, TraceParser,
+    parse_trace_file_entries, parse_trace_lines, strip_bom,
+};
+use log_viewer::pcap::{DEFAULT_PORTS, read_capture};
+use log_viewer::progress_bar;
+use log_viewer::validation::OcppValidator;
 use rusqlite::Connection;
 
+use crate::blueprint::{MeterRoot, blueprint_roots, overview_blueprint, setup_blueprint};
+use crate::export::{CsvExport, Exports, InfluxExport, JsonlExport, ParquetExport, SqliteExport};
+use crate::html_report::HtmlReport;
+use crate::plot::{
+    PathLayout, PlotConfig, TracePlotter, load_data_transfer_schema, plot_log_file_entry,
+};
+use crate::report::{ReportFormat, SessionReport};
+use crate::theme::ThemeMap;
+
 #[derive(Debug, clap::Parser)]
 #[clap(author, version, about)]
 pub struct Args {
     #[command(flatten)]
     exclusive: Exclusive,
+
+    /// Read the arguments that aren't given on the command line from this TOML file, with one key
+    /// per argument, e.g. `extension = ["trace", "log"]`. Defaults to
+    /// `~/.config/ocpp_log_viewer/config.toml` if it exists.
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Save the recording to this .rrd file instead of spawning a viewer.
+    #[arg(long, conflicts_with = "rerun_addr")]
+    output_file: Option<PathBuf>,
+
+    /// Save one recording per charge point to `{station_id}.rrd` in this directory instead of
+    /// spawning a viewer, with the entity paths of each recording not prefixed by the station.
+    /// Implies `--station-id-column`. Energy log files aren't plotted.
+    #[arg(
+        long,
+        value_name = "OUTPUT_DIR",
+        conflicts_with_all = ["output_file", "rerun_addr", "dry_run", "follow", "sqlite_db_path"]
+    )]
+    split_by_station: Option<PathBuf>,
+
+    /// Connect to an already running Rerun viewer at `HOST:PORT` instead of spawning a new one.
+    /// The viewer listens on port 9876 by default.
+    #[arg(long)]
+    rerun_addr: Option<String>,
+
+    /// Log every charge point to its own recording, with the application ID `EVSE_{station_id}`,
+    /// so that the viewer shows them independently. Implies `--station-id-column`. Energy log
+    /// files aren't plotted. A viewer is spawned unless `--rerun-addr` is given, also next to the
+    /// file exports.
+    #[arg(
+        long,
+        conflicts_with_all = ["output_file", "split_by_station", "dry_run", "sqlite_db_path"]
+    )]
+    multi_stream: bool,
+
+    /// Write one CSV row per sampled value to this file. No viewer is spawned unless
+    /// `--output-file` or `--rerun-addr` is given as well.
+    #[arg(long)]
+    csv_output: Option<PathBuf>,
+
+    /// Append one JSON object per MeterValues message to this file. No viewer is spawned unless
+    /// `--output-file` or `--rerun-addr` is given as well.
+    #[arg(long)]
+    jsonl_output: Option<PathBuf>,
+
+    /// Write one InfluxDB line protocol point per sampled value to this file, with nanosecond
+    /// timestamps, for a bulk import with `influx write`. No viewer is spawned unless
+    /// `--output-file` or `--rerun-addr` is given as well.
+    #[arg(long, value_name = "PATH")]
+    influx_output: Option<PathBuf>,
+
+    /// Write one Parquet row per sampled value to this file, for loading into pandas or polars.
+    /// No viewer is spawned unless `--output-file` or `--rerun-addr` is given as well.
+    #[arg(long, value_name = "PATH")]
+    parquet_output: Option<PathBuf>,
+
+    /// Make `--parquet-output` a directory with one file per UTC date, in `date=YYYY-MM-DD`
+    /// subdirectories.
+    #[arg(long, requires = "parquet_output")]
+    parquet_partition_by_date: bool,
+
+    /// Insert one row per sampled value into the `meter_values` table of this SQLite database,
+    /// which is created if it doesn't exist yet. No viewer is spawned unless `--output-file` or
+    /// `--rerun-addr` is given as well.
+    #[arg(long, value_name = "PATH")]
+    sqlite_output: Option<PathBuf>,
+
+    /// Extension of the trace files, can be repeated. `*` reads every file as trace file.
+    ///
+    /// Compressed files are supported as well: files with an additional `.gz` (gzip) or `.zst`
+    /// (zstd) extension, e.g. `a.trace.gz`, are decompressed while reading.
+    #[arg(long, default_value = "trace")]
+    extension: Vec<String>,
+
+    /// TCP port of the WebSocket connections in `--pcap` captures, can be repeated. Connections
+    /// with TLS can't be read, unless it was terminated in front of the captured port.
+    #[arg(long, value_name = "PORT", default_values_t = DEFAULT_PORTS)]
+    pcap_port: Vec<u16>,
+
+    /// Parse all trace lines and print what was found, without logging anything to Rerun. Fails if
+    /// a line with a valid layout and timestamp can't be parsed.
+    #[arg(
+        long,
+        conflicts_with_all = ["output_file", "rerun_addr", "csv_output", "jsonl_output", "influx_output", "parquet_output", "sqlite_output", "sqlite_db_path"]
+    )]
+    dry_run: bool,
+
+    /// After processing the existing trace files, keep watching the trace file directories and
+    /// files and process lines appended to uncompressed trace files until Ctrl-C is pressed.
+    #[arg(long, conflicts_with_all = ["dry_run", "sqlite_db_path"])]
+    follow: bool,
+
+    /// Don't print the summary of the parsed trace lines to stderr.
+    #[arg(long)]
+    no_summary: bool,
+
+    /// Print to stderr why each trace line is skipped, or which measurands it has.
+    #[arg(long)]
+    verbose: bool,
+
+    /// Only print errors: no summaries and no log messages of this tool or of Rerun below the
+    /// error level. With `--dry-run`, the exit code tells whether the trace files parsed.
+    #[arg(long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print a table with one row per transaction to stdout once all trace lines are processed:
+    /// its station, connector, start and end, duration, peak power and energy. The energy is
+    /// taken from meterStart and meterStop, or integrated from the power without them.
+    #[arg(long, conflicts_with_all = ["csv_report", "json_report"])]
+    session_report: bool,
+
+    /// Like `--session-report`, as CSV.
+    #[arg(long, conflicts_with = "json_report")]
+    csv_report: bool,
+
+    /// Like `--session-report`, as a JSON array.
+    #[arg(long)]
+    json_report: bool,
+
+    /// Write a standalone HTML report to this file once all trace lines are processed: the
+    /// sessions, the status transitions of the connectors and a chart per measurand and connector.
+    /// The charts load Chart.js from a CDN when the report is opened. No viewer is spawned unless
+    /// `--output-file` or `--rerun-addr` is given as well.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["dry_run", "sqlite_db_path"])]
+    report_html: Option<PathBuf>,
+
+    /// Save a blueprint with an overview tab per station or connector to this `.rbl` file once all
+    /// trace lines are processed: voltage, current, power, energy and the connector status and
+    /// alerts. Open it in the viewer next to a recording with the same stations.
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_parser = parse_blueprint_path,
+        conflicts_with_all = ["dry_run", "split_by_station", "multi_stream", "sqlite_db_path"]
+    )]
+    blueprint_output: Option<PathBuf>,
+
+    /// Number of threads used to decompress the trace files, defaults to one per CPU.
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Stop after N trace lines that were parsed into messages, e.g. to try out settings on the
+    /// start of large traces. Skipped and invalid lines don't count.
+    #[arg(long, value_name = "N", conflicts_with = "follow")]
+    max_lines: Option<usize>,
+
+    /// Skip trace and log files larger than this, before decompressing them.
+    #[arg(long, value_name = "N", default_value_t = 512)]
+    max_file_size_mb: u64,
+
+    /// Skip entries before this time (`%Y-%m-%d %H:%M:%S`, UTC unless an offset is appended).
+    #[arg(long, value_parser = parse_time_argument)]
+    start_time: Option<DateTime<FixedOffset>>,
+
+    /// Skip entries after this time (`%Y-%m-%d %H:%M:%S`, UTC unless an offset is appended).
+    #[arg(long, value_parser = parse_time_argument)]
+    end_time: Option<DateTime<FixedOffset>>,
+
+    /// Prefix all entity paths with the charge-point ID found in this column of a trace line
+    /// (zero-based, defaults to 4 if given without a value).
+    #[arg(long, num_args = 0..=1, default_missing_value = "4")]
+    station_id_column: Option<usize>,
+
+    /// Only plot trace lines of this charge point. Implies `--station-id-column`.
+    #[arg(long)]
+    station_id_filter: Option<String>,
+
+    /// Only plot messages of this connector, can be repeated. Messages that don't refer to a
+    /// connector, e.g. heartbeats, are kept.
+    #[arg(long = "connector-id", value_name = "N")]
+    connector_id_filter: Vec<u32>,
+
+    /// Only plot messages of this transaction, can be repeated. Combined with `--connector-id`,
+    /// messages have to match both. With `--flat-paths`, the meter values are put below
+    /// `transaction/{id}`.
+    #[arg(long = "transaction-id", value_name = "ID")]
+    transaction_id_filter: Vec<String>,
+
+    /// Only plot sampled values of this measurand, given by its variant name, e.g. `CurrentImport`
+    /// or `voltage`, can be repeated.
+    #[arg(long, value_name = "NAME", value_parser = parse_measurand_name)]
+    include_measurand: Vec<Measurand>,
+
+    /// Don't plot sampled values of this measurand, like `--include-measurand`.
+    #[arg(long, value_name = "NAME", value_parser = parse_measurand_name)]
+    exclude_measurand: Vec<Measurand>,
+
+    /// Only decode and plot calls of this OCPP action, e.g. `StatusNotification`, and their
+    /// results, can be repeated. Calls of other actions are counted as filtered out.
+    #[arg(long = "action", value_name = "ACTION")]
+    action_filter: Vec<String>,
+
+    /// Log the JSON of every OCPP message to `ocpp/raw/{action}`, including the ones without a
+    /// dedicated plot. Results and errors are logged to the action of their call.
+    #[arg(long)]
+    log_all_messages: bool,
+
+    /// Log 0 to `ocpp/ack/{action}` for calls without a CallResult or CallError after this many
+    /// seconds, the received CallResults are logged as 1.
+    #[arg(long, default_value_t = 60, value_name = "N")]
+    ack_timeout_s: u32,
+
+    /// Check the payloads of the calls and their results against the OCPP 1.6 JSON schemas of
+    /// their actions and log the violations to `validation/{action}`. Slows the parsing down
+    /// considerably.
+    #[arg(long)]
+    validate_ocpp: bool,
+
+    /// Directory with the `{action}.json` and `{action}Response.json` schemas to validate against
+    /// instead of the bundled ones, which only cover the calls sent by a charge point.
+    #[arg(long, value_name = "DIR", requires = "validate_ocpp")]
+    ocpp_schema_dir: Option<PathBuf>,
+
+    /// Put all entities below this path, e.g. `site_A/charger_bank_1`, so recordings of several
+    /// runs can be merged in one viewer.
+    #[arg(long, default_value = "", value_name = "PREFIX")]
+    entity_prefix: String,
+
+    /// Put the entities of the OCPP messages below the UTC date of their timestamp, e.g.
+    /// `2024-01-15/station/{station_id}/...`, with tabs per date.
+    #[arg(long)]
+    group_by_date: bool,
+
+    /// Put the entities directly below the station ID, if any, instead of into the
+    /// `station/{station_id}/connector/{connector_id}/tx/{transaction_id}` hierarchy.
+    #[arg(long)]
+    flat_paths: bool,
+
+    /// Colors of the series, either a built-in theme, `default` or `phase-standard` with the IEC
+    /// colors of the phases, or a TOML file with `phases`, `measurands` and `series` tables of
+    /// RGB colors on top of the default theme.
+    #[arg(long, default_value = "default", value_name = "NAME|PATH")]
+    theme: String,
+
+    /// Also log the average of the last N seconds of every sampled value series to
+    /// `{entity_path}/avg{N}s`, next to the raw values.
+    #[arg(long, value_name = "N")]
+    rolling_avg_window_s: Option<u64>,
+
+    /// Only log the first and every Nth value of each meter value series, starting over with every
+    /// transaction. This is plain decimation without averaging, so signals changing faster than
+    /// every N samples may alias.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    sample_rate: Option<u64>,
+
+    /// Also log the rate of change of the register and export measurands to
+    /// `{entity_path}/derivative`, per hour for energy, e.g. W for Wh registers, and per second
+    /// otherwise.
+    #[arg(long)]
+    derivative: bool,
+
+    /// Flag the import currents of L1, L2 and L3 as imbalanced when `(max - min) / max` exceeds
+    /// this ratio, logged to `alerts/phase_imbalance` and `diagnostics/phase_imbalanced`.
+    #[arg(long, default_value_t = 0.1, value_name = "RATIO")]
+    imbalance_threshold: f64,
+
+    /// Nominal phase-to-neutral voltage, e.g. 230 V in the EU or 120 V in the US. Voltages between
+    /// two lines are compared against √3 times this.
+    #[arg(long, default_value_t = 230.0, value_name = "V")]
+    nominal_voltage_v: f64,
+
+    /// Flag voltages deviating more than this many percent from the nominal voltage, logged to
+    /// `alerts/voltage/{phase}` and `diagnostics/voltage_out_of_range/{phase}`.
+    #[arg(long, default_value_t = 10.0, value_name = "PCT")]
+    voltage_tolerance_pct: f64,
+
+    /// Flag temperatures above this many °C, logged to `alerts/temperature/{phase}` and
+    /// `diagnostics/temperature_too_high/{phase}`. Temperatures are colored from blue at 0 °C to
+    /// red at this temperature.
+    #[arg(long, default_value_t = 60.0, value_name = "C")]
+    temperature_max_c: f64,
+
+    /// Nominal grid frequency, e.g. 50 Hz in the EU or 60 Hz in the US.
+    #[arg(long, default_value_t = 50.0, value_name = "F")]
+    nominal_frequency_hz: f64,
+
+    /// Flag frequencies deviating more than this from the nominal frequency, logged to
+    /// `alerts/frequency_deviation`.
+    #[arg(long, default_value_t = 0.5, value_name = "D")]
+    frequency_tolerance_hz: f64,
+
+    /// Flag fan speeds below this many RPM while Power.Active.Import is above zero, a sign of a
+    /// cooling fault, logged to `alerts/fan_stall`.
+    #[arg(long, default_value_t = 100.0, value_name = "N")]
+    rpm_stall_threshold: f64,
+
+    /// Flag a total power factor below this, computed from the active and reactive power of all
+    /// three phases and logged to `alerts/low_power_factor`.
+    #[arg(long, default_value_t = 0.9, value_name = "F")]
+    power_factor_min_alert: f64,
+
+    /// Plot the numeric fields of DataTransfer data to `datatransfer/{vendorId}/{field}`. The JSON
+    /// file maps vendor IDs to the fields to plot and their units, e.g.
+    /// `{"ACME": {"battery_temperature": "Celsius"}}`.
+    #[arg(long, value_name = "PATH")]
+    datatransfer_schema: Option<PathBuf>,
+
+    /// Warn when two heartbeats of a station are further apart than this many seconds.
+    #[arg(long, default_value_t = 90.0)]
+    heartbeat_alarm_threshold_s: f64,
+
+    /// Column of the whitespace-split trace line holding the date (zero-based).
+    #[arg(long, default_value_t = 0)]
+    date_col: usize,
+
+    /// Column of the whitespace-split trace line holding the time (zero-based).
+    #[arg(long, default_value_t = 1)]
+    time_col: usize,
+
+    /// Column of the whitespace-split trace line holding the OCPP JSON (zero-based) [default: 9].
+    /// Without it and `--min-cols`, lines with 9 columns have their JSON in column 8.
+    #[arg(long)]
+    json_col: Option<usize>,
+
+    /// Take everything from `--json-col` to the end of the line as the OCPP JSON, for frames with
+    /// whitespace in them. `--json-col-last false` takes only that column.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        default_value_t = true,
+        default_missing_value = "true",
+        num_args = 0..=1,
+        action = clap::ArgAction::Set
+    )]
+    json_col_last: bool,
+
+    /// Split the trace lines at exactly this character instead of at any whitespace, e.g. `\t`
+    /// for tab-separated logs with spaces in their columns.
+    #[arg(long, value_name = "CHAR", value_parser = parse_delimiter)]
+    delimiter: Option<char>,
+
+    /// Format of the date and time columns of a trace line, joined by a space, replacing
+    /// `%Y-%m-%d %H:%M:%S%.f`. Times without an offset are taken as `--timezone`. RFC 3339
+    /// timestamps in the date column are recognized either way.
+    #[arg(long, value_name = "STRFTIME")]
+    timestamp_format: Option<String>,
+
+    /// IANA timezone of the trace line timestamps without an offset, e.g. `Europe/Berlin`. They
+    /// are converted to UTC, and local times skipped by a DST transition are rejected.
+    #[arg(long, value_name = "TZ", default_value = "UTC")]
+    timezone: Tz,
+
+    /// IANA timezone the timestamps of the summary and session report are printed in.
+    #[arg(long, value_name = "TZ", default_value = "UTC")]
+    display_timezone: Tz,
+
+    /// Process the trace lines in the order they were read instead of sorting them by timestamp,
+    /// for traces that are known to be in order. The trace files are then plotted while they are
+    /// read, without holding them in memory.
+    #[arg(long)]
+    no_sort: bool,
+
+    /// Keep sampled values in the unit they were reported in instead of converting kilo units,
+    /// e.g. kW and kWh, to their base unit.
+    #[arg(long)]
+    no_unit_normalize: bool,
+
+    /// Skip trace lines with fewer columns than this [default: 10].
+    #[arg(long)]
+    min_cols: Option<usize>,
+}
+
+impl Args {
+    /// Whether the parsed data is written to at least one export file.
+    fn has_file_export(&self) -> bool {
+        self.csv_output.is_some()
+            || self.jsonl_output.is_some()
+            || self.influx_output.is_some()
+            || self.parquet_output.is_some()
+            || self.sqlite_output.is_some()
+            || self.report_html.is_some()
+    }
+
+    fn report_format(&self) -> Option<ReportFormat> {
+        if self.csv_report {
+            Some(ReportFormat::Csv)
+        } else if self.json_report {
+            Some(ReportFormat::Json)
+        } else {
+            self.session_report.then_some(ReportFormat::Table)
+        }
+    }
+}
+
+fn parse_time_argument(argument: &str) -> Result<DateTime<FixedOffset>, chrono::ParseError> {
+    DateTime::parse_from_str(argument, "%Y-%m-%d %H:%M:%S %z").or_else(|_| {
+        DateTime::parse_from_str(
+            format!("{} +00:00", argument).as_str(),
+            "%Y-%m-%d %H:%M:%S %z",
+        )
+    })
+}
+
+/// A single character, or `\t` for a tab.
+fn parse_delimiter(argument: &str) -> Result<char, String> {
+    if argument == "\\t" {
+        return Ok('\t');
+    }
+
+    argument
+        .chars()
+        .exactly_one()
+        .map_err(|_| format!("`{}` is not a single character", argument))
+}
+
+fn parse_blueprint_path(argument: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(argument);
+    if !has_file_extension(&path, "rbl") {
+        return Err(format!("`{}` must have the .rbl extension", argument));
+    }
+
+    Ok(path)
 }
 
 #[derive(Debug, clap::Args)]
-#[group(required = true, multiple = false)]
+#[group(required = true, multiple = true)]
 struct Exclusive {
-    /// Path to the trace file.
+    /// Directory that is searched for trace and log files, can be repeated. `-` reads trace lines
+    /// from stdin instead.
     #[arg(short, long)]
-    trace_file_directory: Option<String>,
+    trace_file_directory: Vec<String>,
+
+    /// Path to a single trace or .log file, can be repeated.
+    #[arg(long)]
+    trace_file: Vec<PathBuf>,
+
+    /// URL of a trace file that is downloaded over HTTP or HTTPS, can be repeated. Files with a
+    /// `.gz` or `.zst` extension are decompressed.
+    #[arg(long, value_name = "URL")]
+    trace_url: Vec<String>,
+
+    /// PCAP or PCAPNG capture of WebSocket connections whose OCPP-J frames are read like trace
+    /// lines, with the capture time of their packets, can be repeated. The station ID is the last
+    /// segment of the URL path of the connection.
+    #[arg(long, value_name = "PATH")]
+    pcap: Vec<PathBuf>,
 
     /// Path to SQLite DB
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["trace_file_directory", "trace_file", "trace_url", "pcap"]
+    )]
     sqlite_db_path: Option<String>,
+
+    /// Print the OCPP 1.6 measurands with their default unit and exit.
+    #[arg(long, exclusive = true)]
+    list_measurands: bool,
+
+    /// Print the OCPP 1.6 phases and exit.
+    #[arg(long, exclusive = true)]
+    list_phases: bool,
 }
 
-fn read_file(file_directory: &String, file_extension: &str) -> Vec<String> {
-    let mut contents: Vec<String> = Vec::new();
-    for entry in glob(format!("{}/**/*.{}", file_directory, file_extension).as_str())
-        .expect("Failed to read glob pattern")
-    {
-        match entry {
-            Ok(path) => {
-                match fs::read_to_string(&path) {
-                    // If successful return the files text as `contents`.
-                    // `c` is a local variable.
-                    Ok(c) => c.split("\n").map(|s| s.to_owned()).for_each(|e| {
-                        contents.push(e);
-                    }),
-                    // Handle the `error` case.
-                    Err(_) => {
-                        // Write `msg` to `stderr`.
-                        panic!("Could not read file `{}`", file_directory);
+/// Extensions of compressed files, which are decompressed while reading.
+const COMPRESSED_EXTENSIONS: [&str; 2] = ["gz", "zst"];
+
+/// Whether the file name of `path` ends with `.{extension}`, optionally followed by one of the
+/// compressed extensions.
+fn has_file_extension(path: &Path, extension: &str) -> bool {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let suffix = format!(".{}", extension);
+
+    file_name.ends_with(&suffix)
+        || COMPRESSED_EXTENSIONS.iter().any(|compressed| {
+            file_name
+                .strip_suffix(&format!(".{}", compressed))
+                .is_some_and(|name| name.ends_with(&suffix))
+        })
+}
+
+/// Collects the files with one of `file_extensions` below all `file_directories` plus the
+/// explicitly given `files` with one of them, compressed or not. A `*` extension matches every
+/// file. A file reachable through several inputs is only returned once.
+fn collect_files(
+    file_directories: &[String],
+    files: &[PathBuf],
+    file_extensions: &[String],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let any_extension = file_extensions.iter().any(|ext| ext == "*");
+    let patterns = if any_extension {
+        vec!["*".to_owned()]
+    } else {
+        file_extensions
+            .iter()
+            .flat_map(|ext| {
+                let ext = Pattern::escape(ext);
+                std::iter::once(format!("*.{}", ext)).chain(
+                    COMPRESSED_EXTENSIONS
+                        .iter()
+                        .map(move |compressed| format!("*.{}.{}", ext, compressed)),
+                )
+            })
+            .collect()
+    };
+
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for file_directory in file_directories {
+        for pattern in &patterns {
+            let pattern = format!("{}/**/{}", Pattern::escape(file_directory), pattern);
+            for entry in
+                glob(&pattern).with_context(|| format!("Invalid glob pattern `{}`", pattern))?
+            {
+                match entry {
+                    Ok(path) if path.is_file() => paths.push(path),
+                    Ok(_) => {}
+                    Err(e) => {
+                        re_log::warn!("Could not read `{}`: {}", e.path().display(), e.error())
                     }
-                };
+                }
             }
-            Err(e) => println!("{:?}", e),
         }
     }
 
-    contents
+    paths.extend(
+        files
+            .iter()
+            .filter(|path| {
+                any_extension
+                    || file_extensions
+                        .iter()
+                        .any(|ext| has_file_extension(path, ext))
+            })
+            .cloned(),
+    );
+
+    Ok(paths
+        .into_iter()
+        .unique_by(|path| fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+        .collect())
 }
 
-fn plot_current_import(rec: &RecordingStream, phase: Phase, value: f64) -> anyhow::Result<()> {
-    rec.log(
-        format!("current/import/{:?}", phase),
-        &Scalars::single(value),
-    )?;
+/// Decodes the content of a file to a string, decompressing it first if its extension says it is
+/// compressed.
+fn decode_file(path: &Path, content: Vec<u8>) -> io::Result<String> {
+    let content = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => io::read_to_string(GzDecoder::new(content.as_slice()))?,
+        Some("zst") => io::read_to_string(zstd::Decoder::new(content.as_slice())?)?,
+        _ => {
+            String::from_utf8(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        }
+    };
 
-    Ok(())
+    Ok(strip_bom(&content).to_owned())
 }
 
-fn plot_voltage(rec: &RecordingStream, phase: Phase, value: f64) -> anyhow::Result<()> {
-    rec.log(format!("voltage/{:?}", phase), &Scalars::single(value))?;
+/// Fails for files larger than `max_size_mb`.
+fn check_file_size(size: u64, max_size_mb: u64) -> io::Result<()> {
+    if size > max_size_mb * 1024 * 1024 {
+        return Err(io::Error::new(
+            io::ErrorKind::FileTooLarge,
+            format!(
+                "{} MB is more than --max-file-size-mb {}",
+                size / 1024 / 1024,
+                max_size_mb
+            ),
+        ));
+    }
 
     Ok(())
 }
 
-fn plot_current_offered(rec: &RecordingStream, value: f64) -> anyhow::Result<()> {
-    rec.log("current/offered", &Scalars::single(value))?;
+/// Reads the file, unless it is larger than `max_size_mb`.
+async fn read_file(path: PathBuf, max_size_mb: u64) -> io::Result<Vec<u8>> {
+    check_file_size(tokio::fs::metadata(&path).await?.len(), max_size_mb)?;
 
-    Ok(())
+    tokio::fs::read(path).await
 }
 
-fn plot_power_offered(rec: &RecordingStream, value: f64) -> anyhow::Result<()> {
-    rec.log("power/offered", &Scalars::single(value))?;
-
-    Ok(())
+/// Opens the file to be read line by line, decompressing it on the fly if its extension says it
+/// is compressed, unless it is larger than `max_size_mb`.
+fn open_file(path: &Path, max_size_mb: u64) -> io::Result<Box<dyn BufRead>> {
+    let file = fs::File::open(path)?;
+    check_file_size(file.metadata()?.len(), max_size_mb)?;
+
+    Ok(match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => Box::new(BufReader::new(GzDecoder::new(file))),
+        Some("zst") => Box::new(BufReader::new(zstd::Decoder::new(file)?)),
+        _ => Box::new(BufReader::new(file)),
+    })
 }
 
-fn plot_power_active_import(rec: &RecordingStream, phase: Phase, value: f64) -> anyhow::Result<()> {
-    rec.log(
-        format!("power/active/import/{:?}", phase),
-        &Scalars::single(value),
-    )?;
+/// Parses the lines of `reader` as they are read and passes the entries of each line to
+/// `on_entries`. Returns whether all lines could be read, a read error is logged.
+fn stream_lines(
+    name: &str,
+    reader: impl BufRead,
+    parser: &mut TraceParser,
+    on_entries: &mut impl FnMut(&[TraceFileEntry]) -> anyhow::Result<()>,
+) -> anyhow::Result<bool> {
+    for (index, line) in reader.lines().enumerate() {
+        if parser.is_done() {
+            break;
+        }
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                re_log::error!("Could not read file {}: {}", name, err);
+                return Ok(false);
+            }
+        };
+        let line = if index == 0 { strip_bom(&line) } else { &line };
 
-    Ok(())
+        let trace_file_entries = parser.parse_line(line);
+        if !trace_file_entries.is_empty() {
+            on_entries(&trace_file_entries)?;
+        }
+    }
+
+    Ok(true)
 }
 
-fn plot_power_active_import_sum(rec: &RecordingStream, value: f64) -> anyhow::Result<()> {
-    rec.log("power/active/import/sum", &Scalars::single(value))?;
+/// Like [`read_files`] followed by [`parse_trace_file_entries`], but hands the entries of each
+/// line to `on_entries` right away instead of keeping all lines in memory, which rules out sorting
+/// them. Stdin is read after the files, `downloaded` lines after stdin and the `captured` frames
+/// last. Returns the number of files that couldn't be read.
+fn stream_trace_files(
+    paths: &[PathBuf],
+    stdin: bool,
+    downloaded: &[String],
+    captured: Vec<TraceLine>,
+    max_size_mb: u64,
+    parser: &mut TraceParser,
+    mut on_entries: impl FnMut(&[TraceFileEntry]) -> anyhow::Result<()>,
+) -> anyhow::Result<usize> {
+    let mut failed_files = 0;
+    for path in paths {
+        let reader = match open_file(path, max_size_mb) {
+            Ok(reader) => reader,
+            Err(err) if err.kind() == io::ErrorKind::FileTooLarge => {
+                re_log::warn!("Skipping file {}: {}", path.display(), err);
+                failed_files += 1;
+                continue;
+            }
+            Err(err) => {
+                re_log::error!("Could not read file {}: {}", path.display(), err);
+                failed_files += 1;
+                continue;
+            }
+        };
+        if !stream_lines(&path.display().to_string(), reader, parser, &mut on_entries)? {
+            failed_files += 1;
+        }
+    }
+    if stdin {
+        stream_lines("stdin", io::stdin().lock(), parser, &mut on_entries)?;
+    }
+    for line in downloaded {
+        let trace_file_entries = parser.parse_line(line);
+        if !trace_file_entries.is_empty() {
+            on_entries(&trace_file_entries)?;
+        }
+    }
+    for trace_line in captured {
+        let trace_file_entries = parser.parse_split_line(trace_line);
+        if !trace_file_entries.is_empty() {
+            on_entries(&trace_file_entries)?;
+        }
+    }
 
-    Ok(())
+    Ok(failed_files)
 }
 
-fn plot_pv_production(rec: &RecordingStream, value: f64) -> anyhow::Result<()> {
-    rec.log("/log/pv_production", &Scalars::single(value))?;
+/// Reads the WebSocket frames of the captures, unless they are larger than `max_size_mb`, and
+/// returns them with the number of captures that couldn't be read.
+fn read_captures(paths: &[PathBuf], ports: &[u16], max_size_mb: u64) -> (Vec<TraceLine>, usize) {
+    let mut trace_lines = Vec::new();
+    let mut failed_captures = 0;
+    for path in paths {
+        let read = fs::metadata(path)
+            .and_then(|metadata| check_file_size(metadata.len(), max_size_mb))
+            .and_then(|()| fs::read(path))
+            .map_err(anyhow::Error::from)
+            .and_then(|data| read_capture(&data, ports));
+        match read {
+            Ok(captured) => trace_lines.extend(captured),
+            Err(err) => {
+                re_log::error!("Could not read capture {}: {}", path.display(), err);
+                failed_captures += 1;
+            }
+        }
+    }
 
-    Ok(())
+    (trace_lines, failed_captures)
 }
 
-fn plot_battery_load(rec: &RecordingStream, value: f64) -> anyhow::Result<()> {
-    rec.log("/log/battery_load", &Scalars::single(value))?;
+/// Reads the files concurrently, decompresses them in parallel and returns their lines in the
+/// order of `paths`, and the number of files that couldn't be read.
+async fn read_files(paths: &[PathBuf], max_size_mb: u64) -> (Vec<String>, usize) {
+    let progress = progress_bar(
+        ProgressBar::new(paths.len() as u64),
+        "{bar:40} {pos}/{len} files read",
+    );
+    let reads = paths
+        .iter()
+        .cloned()
+        .map(|path| tokio::spawn(read_file(path, max_size_mb)))
+        .collect::<Vec<_>>();
+    let mut contents: Vec<(&PathBuf, io::Result<Vec<u8>>)> = Vec::with_capacity(paths.len());
+    for (path, read) in paths.iter().zip(reads) {
+        contents.push((path, read.await.expect("File read task should not panic")));
+        progress.inc(1);
+    }
+    progress.finish();
 
-    Ok(())
-}
+    let results = contents
+        .into_par_iter()
+        .map(|(path, content)| (path, content.and_then(|content| decode_file(path, content))))
+        .collect::<Vec<_>>();
 
-fn plot_ev_import(rec: &RecordingStream, value: f64) -> anyhow::Result<()> {
-    rec.log("/log/ev_import", &Scalars::single(value))?;
+    let mut contents: Vec<String> = Vec::new();
+    let mut failed_files = 0;
+    for (path, result) in results {
+        match result {
+            Ok(c) => c.split("\n").map(|s| s.to_owned()).for_each(|e| {
+                contents.push(e);
+            }),
+            Err(err) if err.kind() == io::ErrorKind::FileTooLarge => {
+                re_log::warn!("Skipping file {}: {}", path.display(), err);
+                failed_files += 1;
+            }
+            // One unreadable file shouldn't keep the others from being plotted.
+            Err(err) => {
+                re_log::error!("Could not read file {}: {}", path.display(), err);
+                failed_files += 1;
+            }
+        };
+    }
 
-    Ok(())
+    (contents, failed_files)
 }
 
-fn plot_load_overall(rec: &RecordingStream, value: f64) -> anyhow::Result<()> {
-    rec.log("/log/load_overall", &Scalars::single(value))?;
+/// Downloads the trace files in order and returns their lines.
+async fn download_files(urls: &[String]) -> anyhow::Result<Vec<String>> {
+    let client = reqwest::Client::new();
+    let mut contents: Vec<String> = Vec::new();
+    for url in urls {
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Could not download `{}`", url))?;
+        if response.status() != reqwest::StatusCode::OK {
+            anyhow::bail!("Downloading `{}` failed with {}", url, response.status());
+        }
 
-    Ok(())
+        let path = PathBuf::from(response.url().path());
+        let content = response
+            .bytes()
+            .await
+            .with_context(|| format!("Could not download `{}`", url))?;
+        contents.extend(
+            decode_file(&path, content.to_vec())
+                .with_context(|| format!("Could not decode `{}`", url))?
+                .split("\n")
+                .map(|s| s.to_owned()),
+        );
+    }
+
+    Ok(contents)
 }
 
-fn plot_overproduction(rec: &RecordingStream, value: f64) -> anyhow::Result<()> {
-    rec.log("/log/overproduction", &Scalars::single(value))?;
+/// Reads the trace lines piped into the viewer, selected with `--trace-file-directory -`.
+async fn read_stdin() -> anyhow::Result<Vec<String>> {
+    let mut content = String::new();
+    tokio::io::stdin().read_to_string(&mut content).await?;
 
-    Ok(())
+    Ok(strip_bom(&content)
+        .split("\n")
+        .map(|s| s.to_owned())
+        .collect())
 }
 
-fn plot_meter_value_readings(
-    rec: &RecordingStream,
-    trace_file_entry: &TraceFileEntry,
-) -> anyhow::Result<()> {
-    rec.set_timestamp_secs_since_epoch("time", trace_file_entry.timestamp as f64);
-
-    plot_current_import(rec, Phase::L1, trace_file_entry.current_import.l1)?;
-    plot_current_import(rec, Phase::L2, trace_file_entry.current_import.l2)?;
-    plot_current_offered(rec, trace_file_entry.current_offered)?;
-    plot_power_offered(rec, trace_file_entry.power_offered)?;
-
-    plot_voltage(rec, Phase::L1, trace_file_entry.voltage.l1)?;
-    plot_voltage(rec, Phase::L2, trace_file_entry.voltage.l2)?;
-    plot_voltage(rec, Phase::L3, trace_file_entry.voltage.l3)?;
-
-    plot_power_active_import(rec, Phase::L1, trace_file_entry.power_active_import.l1)?;
-    plot_power_active_import(rec, Phase::L2, trace_file_entry.power_active_import.l2)?;
-    plot_power_active_import(rec, Phase::L3, trace_file_entry.power_active_import.l3)?;
-
-    plot_power_active_import_sum(
-        rec,
-        trace_file_entry.power_active_import.l2
-            + trace_file_entry.power_active_import.l2
-            + trace_file_entry.power_active_import.l3,
-    )?;
+fn print_measurands() {
+    for measurand in &MEASURANDS {
+        let (description, unit) = measurand_description(measurand);
+        println!("{:<32} {:<8} {}", ocpp_name(measurand), unit, description);
+    }
+}
 
-    Ok(())
+fn print_phases() {
+    for phase in &PHASES {
+        println!("{:<8} {}", ocpp_name(phase), phase_description(phase));
+    }
 }
 
-fn plot_log_file_entry(rec: &RecordingStream, log_file_entry: &LogFileEntry) -> anyhow::Result<()> {
-    rec.set_timestamp_secs_since_epoch("time", log_file_entry.timestamp);
+fn setup_recording_stream(args: &Args, layout: &PathLayout) -> anyhow::Result<RecordingStream> {
+    // With `--split-by-station`, every station gets its own recording later on.
+    if args.dry_run || args.split_by_station.is_some() {
+        return Ok(RecordingStream::disabled());
+    }
+
+    let builder = rerun::RecordingStreamBuilder::new("OcppMeter values")
+        .with_blueprint(setup_blueprint(layout, &[], &[]));
 
-    plot_pv_production(rec, log_file_entry.pv_overproduction)?;
-    plot_battery_load(rec, log_file_entry.battery_load)?;
-    plot_ev_import(rec, log_file_entry.ev_import)?;
-    plot_load_overall(rec, log_file_entry.load_overall)?;
-    plot_overproduction(rec, log_file_entry.overproduction)?;
+    if let Some(output_file) = &args.output_file {
+        if output_file.extension().is_none_or(|ext| ext != "rrd") {
+            anyhow::bail!(
+                "Output file `{}` must have the .rrd extension",
+                output_file.display()
+            );
+        }
 
-    Ok(())
-}
+        // Fail before any line is processed instead of when the first message is flushed.
+        fs::File::create(output_file)
+            .with_context(|| format!("Could not create output file `{}`", output_file.display()))?;
 
-struct CurrentImport {
-    l1: f64,
-    l2: f64,
-}
+        return Ok(builder.save(output_file)?);
+    }
 
-struct Voltage {
-    l1: f64,
-    l2: f64,
-    l3: f64,
-}
+    if let Some(rerun_addr) = &args.rerun_addr {
+        // The gRPC sink connects lazily, so check that a viewer is listening to fail early.
+        TcpStream::connect(rerun_addr)
+            .with_context(|| format!("Could not connect to Rerun viewer at `{}`", rerun_addr))?;
+
+        // With `--multi-stream`, every station connects with its own recording later on.
+        if args.multi_stream {
+            return Ok(RecordingStream::disabled());
+        }
+
+        return Ok(builder.connect_grpc_opts(format!("rerun+http://{}/proxy", rerun_addr))?);
+    }
+
+    if args.has_file_export() || args.multi_stream {
+        return Ok(RecordingStream::disabled());
+    }
 
-struct PowerActiveImport {
-    l1: f64,
-    l2: f64,
-    l3: f64,
+    Ok(builder.spawn()?)
 }
 
-struct TraceFileEntry {
-    timestamp: f64,
-    current_import: CurrentImport,
-    current_offered: f64,
-    power_offered: f64,
-    voltage: Voltage,
-    power_active_import: PowerActiveImport,
+/// Recording of a station with `--multi-stream`, and the state of its blueprint and plots.
+struct StationStream {
+    rec: RecordingStream,
+    trace_plotter: TracePlotter,
+    stations: Vec<MeterRoot>,
+    meter_roots: Vec<MeterRoot>,
 }
 
-fn parse_meter_value_readings(contents: &Vec<String>) -> anyhow::Result<Vec<TraceFileEntry>> {
-    let mut trace_file_entries: Vec<TraceFileEntry> = Vec::new();
-    for line in contents {
-        let line_parts = line
-            .split(char::is_whitespace)
-            .map(|s| s.to_owned())
-            .collect::<Vec<_>>();
-
-        let (date, time, json) = if line_parts.len() == 10 {
-            (
-                line_parts[0].clone(),
-                line_parts[1].clone(),
-                line_parts[9].clone(),
-            )
-        } else if line_parts.len() == 9 {
-            (
-                line_parts[0].clone(),
-                line_parts[1].clone(),
-                line_parts[8].clone(),
-            )
-        } else {
-            continue;
-        };
+/// With `--multi-stream`, the recordings of the stations seen so far, keyed by station ID. They
+/// are created when the first entry of a station is plotted.
+struct StationStreams {
+    /// Viewer the recordings connect to, a new one is spawned without it.
+    rerun_addr: Option<String>,
+    layout: PathLayout,
+    plot_config: PlotConfig,
+    streams: BTreeMap<String, StationStream>,
+}
 
-        let date_time = format!("{} {} +00:00", date.as_str().replace("[", ""), time);
-        if date_time.is_empty() {
-            continue;
+impl StationStreams {
+    fn new(rerun_addr: Option<String>, layout: &PathLayout, plot_config: &PlotConfig) -> Self {
+        Self {
+            rerun_addr,
+            layout: layout.clone(),
+            plot_config: plot_config.clone(),
+            streams: BTreeMap::new(),
         }
+    }
 
-        let timestamp = match DateTime::parse_from_str(date_time.as_str(), "%Y-%m-%d %H:%M:%S %z") {
-            Ok(d) => d,
-            _ => continue,
+    /// The recording of `station_id`, with its blueprint updated for `trace_file_entries`.
+    fn stream(
+        &mut self,
+        station_id: &str,
+        trace_file_entries: &[TraceFileEntry],
+    ) -> anyhow::Result<&mut StationStream> {
+        let stream = match self.streams.entry(station_id.to_owned()) {
+            btree_map::Entry::Occupied(entry) => entry.into_mut(),
+            btree_map::Entry::Vacant(entry) => {
+                let application_id = format!(
+                    "EVSE_{}",
+                    if station_id.is_empty() {
+                        "unknown"
+                    } else {
+                        station_id
+                    }
+                );
+                let builder = rerun::RecordingStreamBuilder::new(application_id)
+                    .with_blueprint(setup_blueprint(&self.layout, &[], &[]));
+                let rec = match &self.rerun_addr {
+                    Some(rerun_addr) => {
+                        builder.connect_grpc_opts(format!("rerun+http://{}/proxy", rerun_addr))?
+                    }
+                    None => builder.spawn()?,
+                };
+
+                entry.insert(StationStream {
+                    trace_plotter: TracePlotter::new(&rec, self.plot_config.clone()),
+                    rec,
+                    stations: Vec::new(),
+                    meter_roots: Vec::new(),
+                })
+            }
         };
+        update_blueprint(
+            &stream.rec,
+            &self.layout,
+            &mut stream.stations,
+            &mut stream.meter_roots,
+            trace_file_entries,
+        )?;
+
+        Ok(stream)
+    }
 
-        if let Ok(meter_vaules_request) = serde_json::from_str::<
-            rust_ocpp::v1_6::messages::meter_values::MeterValuesRequest,
-        >(json.as_str())
-        {
-            let mut current_import_l1: Option<f64> = None;
-            let mut current_import_l2: Option<f64> = None;
-
-            let mut current_offered: Option<f64> = None;
-            let mut power_offered: Option<f64> = None;
-
-            let mut voltage_l1: Option<f64> = None;
-            let mut voltage_l2: Option<f64> = None;
-            let mut voltage_l3: Option<f64> = None;
-
-            let mut power_active_import_l1: Option<f64> = None;
-            let mut power_active_import_l2: Option<f64> = None;
-            let mut power_active_import_l3: Option<f64> = None;
-
-            for meter_value in &meter_vaules_request.meter_value {
-                for sampled_value in &meter_value.sampled_value {
-                    match sampled_value.measurand {
-                        Some(rust_ocpp::v1_6::types::Measurand::CurrentImport) => {
-                            match sampled_value.phase {
-                                Some(rust_ocpp::v1_6::types::Phase::L1) => {
-                                    current_import_l1 =
-                                        Some(sampled_value.value.parse::<f64>().unwrap_or(0.0))
-                                }
-                                Some(rust_ocpp::v1_6::types::Phase::L2) => {
-                                    current_import_l2 =
-                                        Some(sampled_value.value.parse::<f64>().unwrap_or(0.0))
-                                }
-                                _ => {}
-                            }
-                        }
-                        Some(rust_ocpp::v1_6::types::Measurand::CurrentOffered) => {
-                            current_offered =
-                                Some(sampled_value.value.parse::<f64>().unwrap_or(0.0));
-                        }
-                        Some(rust_ocpp::v1_6::types::Measurand::PowerOffered) => {
-                            power_offered = Some(sampled_value.value.parse::<f64>().unwrap_or(0.0));
-                        }
-                        Some(rust_ocpp::v1_6::types::Measurand::PowerActiveImport) => {
-                            match sampled_value.phase {
-                                Some(rust_ocpp::v1_6::types::Phase::L1) => {
-                                    power_active_import_l1 =
-                                        Some(sampled_value.value.parse::<f64>().unwrap_or(0.0))
-                                }
-                                Some(rust_ocpp::v1_6::types::Phase::L2) => {
-                                    power_active_import_l2 =
-                                        Some(sampled_value.value.parse::<f64>().unwrap_or(0.0))
-                                }
-                                Some(rust_ocpp::v1_6::types::Phase::L3) => {
-                                    power_active_import_l3 =
-                                        Some(sampled_value.value.parse::<f64>().unwrap_or(0.0))
-                                }
-                                _ => {}
-                            }
-                        }
-                        Some(rust_ocpp::v1_6::types::Measurand::Voltage) => {
-                            match sampled_value.phase {
-                                Some(rust_ocpp::v1_6::types::Phase::L1) => {
-                                    voltage_l1 =
-                                        Some(sampled_value.value.parse::<f64>().unwrap_or(0.0))
-                                }
-                                Some(rust_ocpp::v1_6::types::Phase::L2) => {
-                                    voltage_l2 =
-                                        Some(sampled_value.value.parse::<f64>().unwrap_or(0.0))
-                                }
-                                Some(rust_ocpp::v1_6::types::Phase::L3) => {
-                                    voltage_l3 =
-                                        Some(sampled_value.value.parse::<f64>().unwrap_or(0.0))
-                                }
-                                _ => {}
-                            }
-                        }
-                        _ => {}
-                    }
-                }
+    /// Like [`TracePlotter::finish_sessions`] for every station, printing their summaries.
+    fn finish(&mut self, print_summary: bool) -> anyhow::Result<()> {
+        for stream in self.streams.values_mut() {
+            stream.trace_plotter.finish_sessions()?;
+            stream.trace_plotter.finish_connectors()?;
+            if print_summary {
+                stream.trace_plotter.print_summary();
             }
+            stream.rec.flush_blocking()?;
+        }
 
-            trace_file_entries.push(TraceFileEntry {
-                timestamp: timestamp.timestamp() as f64,
-                current_import: CurrentImport {
-                    l1: current_import_l1.unwrap_or(0.0),
-                    l2: current_import_l2.unwrap_or(0.0),
-                },
-                current_offered: current_offered.unwrap_or(0.0),
-                power_offered: power_offered.unwrap_or(0.0),
-                voltage: Voltage {
-                    l1: voltage_l1.unwrap_or(0.0),
-                    l2: voltage_l2.unwrap_or(0.0),
-                    l3: voltage_l3.unwrap_or(0.0),
-                },
-                power_active_import: PowerActiveImport {
-                    l1: power_active_import_l1.unwrap_or(0.0),
-                    l2: power_active_import_l2.unwrap_or(0.0),
-                    l3: power_active_import_l3.unwrap_or(0.0),
+        Ok(())
+    }
+}
+
+/// Adds the stations and meter value roots of the entries that `stations` and `meter_roots` don't
+/// have yet, and sends a new blueprint with them.
+fn update_blueprint(
+    rec: &RecordingStream,
+    layout: &PathLayout,
+    stations: &mut Vec<MeterRoot>,
+    meter_roots: &mut Vec<MeterRoot>,
+    trace_file_entries: &[TraceFileEntry],
+) -> anyhow::Result<()> {
+    let (new_stations, new_meter_roots) = blueprint_roots(layout, trace_file_entries);
+    // Also without a recording, for `--blueprint-output`.
+    if new_stations
+        .iter()
+        .any(|station| !stations.contains(station))
+        || new_meter_roots
+            .iter()
+            .any(|root| !meter_roots.contains(root))
+    {
+        *stations = stations
+            .drain(..)
+            .chain(new_stations)
+            .unique()
+            .sorted()
+            .collect();
+        *meter_roots = meter_roots
+            .drain(..)
+            .chain(new_meter_roots)
+            .unique()
+            .sorted()
+            .collect();
+        if rec.is_enabled() {
+            setup_blueprint(layout, stations, meter_roots).send(
+                rec,
+                BlueprintActivation {
+                    make_active: true,
+                    make_default: true,
                 },
-            });
+            )?;
         }
     }
 
-    Ok(trace_file_entries)
+    Ok(())
 }
 
-struct LogFileEntry {
-    timestamp: f64,
-    pv_overproduction: f64,
-    battery_load: f64,
-    ev_import: f64,
-    load_overall: f64,
-    overproduction: f64,
-}
+/// Saves the overview blueprint of the stations and meter value roots to an `.rbl` file, for the
+/// application ID of the recordings.
+fn save_blueprint(
+    path: &Path,
+    layout: &PathLayout,
+    stations: &[MeterRoot],
+    meter_roots: &[MeterRoot],
+) -> anyhow::Result<()> {
+    let rec = rerun::RecordingStreamBuilder::new("OcppMeter values")
+        .blueprint()
+        .save(path)
+        .with_context(|| format!("Could not create blueprint output `{}`", path.display()))?;
+    overview_blueprint(layout, stations, meter_roots).send(
+        &rec,
+        BlueprintActivation {
+            make_active: true,
+            make_default: true,
+        },
+    )?;
+    rec.flush_blocking()?;
 
-fn parse_log_file_entries(contents: &Vec<String>) -> anyhow::Result<Vec<LogFileEntry>> {
-    let mut log_file_entries: Vec<LogFileEntry> = Vec::new();
+    Ok(())
+}
 
-    let re = Regex::new(r"([a-zA-Z]+) (-?[0-9]+(\.[0-9]+)?) \+ (-?[0-9]+(\.[0-9]+)?) \+ (-?[0-9]+(\.[0-9]+)?) \+ (-?[0-9]+(\.[0-9]+)?) = (-?[0-9]+(\.[0-9]+)?)").unwrap();
-    let mut average_count = 0;
-    let mut pv_overproduction_average = 0.0;
-    let mut battery_load_average = 0.0;
-    let mut ev_import_average = 0.0;
-    let mut load_overall_average = 0.0;
-    let mut overproduction_average = 0.0;
+/// Plots the entries, into the recordings of their stations with `station_streams`, writes their
+/// meter values to the export files and adds them to the session report.
+fn log_trace_file_entries(
+    rec: &RecordingStream,
+    trace_plotter: &mut TracePlotter,
+    station_streams: &mut Option<StationStreams>,
+    exports: &mut Exports,
+    session_report: &mut Option<SessionReport>,
+    html_report: &mut Option<HtmlReport>,
+    trace_file_entries: &[TraceFileEntry],
+) -> anyhow::Result<()> {
+    for station_entries in trace_file_entries.chunk_by(|a, b| a.station_id == b.station_id) {
+        let (rec, trace_plotter) = match station_streams {
+            Some(station_streams) => {
+                let station_id = station_entries[0].station_id.as_deref().unwrap_or_default();
+                let stream = station_streams.stream(station_id, station_entries)?;
+                (&stream.rec, &mut stream.trace_plotter)
+            }
+            None => (rec, &mut *trace_plotter),
+        };
 
-    for line in contents {
-        let line_parts = line
-            .split(char::is_whitespace)
-            .map(|s| s.to_owned())
-            .collect::<Vec<_>>();
+        for trace_file_entry in station_entries {
+            if rec.is_enabled() {
+                trace_plotter.plot_trace_file_entry(trace_file_entry)?;
+            }
+        }
+    }
 
-        if line_parts.len() < 2 {
-            continue;
+    for trace_file_entry in trace_file_entries {
+        if let Some(session_report) = session_report {
+            session_report.add(trace_file_entry);
         }
-        let (date, time) = (line_parts[0].clone(), line_parts[1].clone());
-        let date_time = format!("{} {} +00:00", date.as_str().replace("[", ""), time);
-        if date_time.is_empty() {
-            continue;
+        if let Some(html_report) = html_report {
+            html_report.add(trace_file_entry);
         }
 
-        let timestamp = match DateTime::parse_from_str(date_time.as_str(), "%Y-%m-%d %H:%M:%S %z") {
-            Ok(d) => d,
-            _ => continue,
-        };
+        if let OcppMessage::MeterValues(meter_values) = &trace_file_entry.message {
+            exports.write_meter_values(
+                &trace_file_entry.timestamp,
+                trace_file_entry.station_id.as_deref(),
+                meter_values,
+            )?;
+        }
+    }
 
-        let Some(caps) = re.captures(line.as_str()) else {
-            continue;
-        };
+    Ok(())
+}
 
-        let pv_overproduction = caps[2].parse::<f64>()?;
-        let battery_load = caps[6].parse::<f64>()?;
-        let ev_import = caps[8].parse::<f64>()?;
-        let load_overall = (caps[4].parse::<f64>()? + caps[8].parse::<f64>()?).abs();
-        let overproduction = if caps[10].parse::<f64>()? >= 0.0 {
-            caps[10].parse::<f64>()?
-        } else {
-            0.0
-        };
+/// Plots the entries of every station into its own `{station_id}.rrd` in `output_dir`, with the
+/// station IDs removed so that the entity paths aren't prefixed by them. Entries without a station
+/// ID go to `unknown.rrd`.
+fn split_by_station(
+    output_dir: &Path,
+    layout: &PathLayout,
+    plot_config: &PlotConfig,
+    trace_file_entries: Vec<TraceFileEntry>,
+    print_summary: bool,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(output_dir).with_context(|| {
+        format!(
+            "Could not create output directory `{}`",
+            output_dir.display()
+        )
+    })?;
+
+    let entries_by_station = trace_file_entries
+        .into_iter()
+        .map(|mut trace_file_entry| {
+            let station_id = trace_file_entry.station_id.take();
+            (station_id, trace_file_entry)
+        })
+        .into_group_map();
+
+    let mut output_files = Vec::new();
+    for (station_id, trace_file_entries) in entries_by_station
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+    {
+        // Station IDs come from the trace lines, keep them from escaping the output directory.
+        let file_name = station_id.as_deref().unwrap_or("unknown").replace(
+            |c: char| !c.is_ascii_alphanumeric() && !"-_.".contains(c),
+            "_",
+        );
+        let output_file = output_dir.join(format!("{}.rrd", file_name));
+
+        let (stations, meter_roots) = blueprint_roots(layout, &trace_file_entries);
+        let rec = rerun::RecordingStreamBuilder::new("OcppMeter values")
+            .with_blueprint(setup_blueprint(layout, &stations, &meter_roots))
+            .save(&output_file)
+            .with_context(|| format!("Could not create output file `{}`", output_file.display()))?;
+        let mut trace_plotter = TracePlotter::new(&rec, plot_config.clone());
+        for trace_file_entry in &trace_file_entries {
+            trace_plotter.plot_trace_file_entry(trace_file_entry)?;
+        }
+        trace_plotter.finish_sessions()?;
+        trace_plotter.finish_connectors()?;
+        if print_summary {
+            trace_plotter.print_summary();
+        }
+        rec.flush_blocking()?;
 
-        if log_file_entries.is_empty() {
-            log_file_entries.push(LogFileEntry {
-                timestamp: timestamp.timestamp() as f64,
-                pv_overproduction,
-                battery_load,
-                ev_import,
-                load_overall,
-                overproduction,
-            });
-        } else {
-            let time_delta =
-                timestamp.timestamp() as f64 - log_file_entries.last().unwrap().timestamp;
-            let threshold = chrono::Duration::minutes(5).as_seconds_f64();
-
-            if time_delta >= threshold {
-                log_file_entries.push(LogFileEntry {
-                    timestamp: timestamp.timestamp() as f64,
-                    pv_overproduction: pv_overproduction_average / average_count as f64,
-                    battery_load: battery_load_average / average_count as f64,
-                    ev_import: ev_import_average / average_count as f64,
-                    load_overall: load_overall_average / average_count as f64,
-                    overproduction: overproduction_average / average_count as f64,
-                });
-
-                average_count = 0;
-                pv_overproduction_average = 0.0;
-                battery_load_average = 0.0;
-                ev_import_average = 0.0;
-                load_overall_average = 0.0;
-                overproduction_average = 0.0;
-            } else {
-                pv_overproduction_average += pv_overproduction;
-                battery_load_average += battery_load;
-                ev_import_average += ev_import;
-                load_overall_average += load_overall;
-                overproduction_average += overproduction;
+        output_files.push(output_file);
+    }
 
-                average_count += 1;
-            }
-        }
+    for output_file in output_files {
+        println!("{}", output_file.display());
     }
 
-    Ok(log_file_entries)
+    Ok(())
 }
 
-fn setup_blueprint() -> Blueprint {
-    Blueprint::new(Grid::new(vec![ContainerLike::from(Tabs::new(vec![
-        ContainerLike::from(Vertical::new(vec![
-            ContainerLike::from(Grid::new(vec![
-                ContainerLike::from(
-                    TimeSeriesView::new("Current")
-                        .with_origin("/")
-                        .with_contents(vec!["current/**"]),
-                ),
-                ContainerLike::from(
-                    TimeSeriesView::new("Power")
-                        .with_origin("/")
-                        .with_contents(vec!["power/**"]),
-                ),
-            ])),
-            ContainerLike::from(
-                TimeSeriesView::new("Voltage")
-                    .with_origin("/")
-                    .with_contents(vec!["voltage/**"]),
-            ),
-        ])),
-        ContainerLike::from(Grid::new(vec![ContainerLike::from(
-            TimeSeriesView::new("Log")
-                .with_origin("/")
-                .with_contents(vec!["/log/**"]),
-        )])),
-    ]))]))
-    .with_time_panel(
-        TimePanel::new()
-            .with_state(PanelState::Collapsed)
-            .with_timeline("time")
-            .with_loop_mode(LoopMode::Selection)
-            .with_play_state(PlayState::Following),
-    )
-}
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut args = config::parse_args()?;
+    if args.verbose {
+        re_log::setup_logging_with_filter(&re_log::log_filter_from_env_or_default("debug"));
+    } else if args.quiet {
+        // Not from `RUST_LOG`, which may well be more verbose.
+        re_log::setup_logging_with_filter("error");
+        args.no_summary = true;
+    } else {
+        re_log::setup_logging();
+    }
+    if args.exclusive.list_measurands {
+        print_measurands();
+        return Ok(());
+    }
+    if args.exclusive.list_phases {
+        print_phases();
+        return Ok(());
+    }
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+    let layout = PathLayout {
+        prefix: args.entity_prefix.trim_matches('/').to_owned(),
+        group_by_date: args.group_by_date,
+        date: None,
+        flat: args.flat_paths,
+        transaction_prefix: !args.transaction_id_filter.is_empty(),
+    };
+    let theme = ThemeMap::load(&args.theme)?;
+    let data_transfer_schema = args
+        .datatransfer_schema
+        .as_deref()
+        .map(load_data_transfer_schema)
+        .transpose()?
+        .unwrap_or_default();
+    let rec = setup_recording_stream(&args, &layout)?;
+    let plot_config = PlotConfig {
+        heartbeat_alarm_threshold_s: args.heartbeat_alarm_threshold_s,
+        rolling_avg_window_s: args.rolling_avg_window_s,
+        sample_rate: args.sample_rate,
+        derivative: args.derivative,
+        imbalance_threshold: args.imbalance_threshold,
+        nominal_voltage_v: args.nominal_voltage_v,
+        voltage_tolerance_pct: args.voltage_tolerance_pct,
+        temperature_max_c: args.temperature_max_c,
+        nominal_frequency_hz: args.nominal_frequency_hz,
+        frequency_tolerance_hz: args.frequency_tolerance_hz,
+        rpm_stall_threshold: args.rpm_stall_threshold,
+        power_factor_min_alert: args.power_factor_min_alert,
+        data_transfer_schema,
+        layout: layout.clone(),
+        theme,
+    };
+    let mut trace_plotter = TracePlotter::new(&rec, plot_config.clone());
+    let mut station_streams = args
+        .multi_stream
+        .then(|| StationStreams::new(args.rerun_addr.clone(), &layout, &plot_config));
+    let mut exports = Exports {
+        csv: args
+            .csv_output
+            .as_deref()
+            .map(CsvExport::create)
+            .transpose()?,
+        jsonl: args
+            .jsonl_output
+            .as_deref()
+            .map(JsonlExport::open)
+            .transpose()?,
+        influx: args
+            .influx_output
+            .as_deref()
+            .map(InfluxExport::create)
+            .transpose()?,
+        parquet: args
+            .parquet_output
+            .as_deref()
+            .map(|path| ParquetExport::create(path, args.parquet_partition_by_date))
+            .transpose()?,
+        sqlite: args
+            .sqlite_output
+            .as_deref()
+            .map(SqliteExport::open)
+            .transpose()?,
+    };
+    let mut session_report = args
+        .report_format()
+        .map(|format| SessionReport::new(format, args.display_timezone));
+    let mut html_report = args
+        .report_html
+        .is_some()
+        .then(|| HtmlReport::new(args.display_timezone));
+    let time_range = TimeRange::new(args.start_time, args.end_time);
+    let measurand_filter = MeasurandFilter {
+        include: args.include_measurand,
+        exclude: args.exclude_measurand,
+    };
+
+    if !args.exclusive.trace_file_directory.is_empty()
+        || !args.exclusive.trace_file.is_empty()
+        || !args.exclusive.trace_url.is_empty()
+        || !args.exclusive.pcap.is_empty()
+    {
+        let (stdin, trace_file_directories): (Vec<String>, Vec<String>) = args
+            .exclusive
+            .trace_file_directory
+            .into_iter()
+            .partition(|trace_file_directory| trace_file_directory == "-");
+
+        let trace_files = collect_files(
+            &trace_file_directories,
+            &args.exclusive.trace_file,
+            &args.extension,
+        )?;
+        let (log_contents, failed_log_files) = read_files(
+            &collect_files(
+                &trace_file_directories,
+                &args.exclusive.trace_file,
+                &["log".to_owned()],
+            )?,
+            args.max_file_size_mb,
+        )
+        .await;
+
+        let default_format = LineFormat::default();
+        let parse_config = ParseConfig {
+            line_format: LineFormat {
+                date_column: args.date_col,
+                time_column: args.time_col,
+                json_column: args.json_col.unwrap_or(default_format.json_column),
+                delimiter: args.delimiter,
+                json_col_last: args.json_col_last,
+                station_id_column: args.station_id_column.or((args.station_id_filter.is_some()
+                    || args.split_by_station.is_some()
+                    || args.multi_stream)
+                    .then_some(4)),
+                min_columns: args.min_cols.unwrap_or(default_format.min_columns),
+                // The 9-column traces only go with the default layout.
+                short_json_column: default_format
+                    .short_json_column
+                    .filter(|_| args.json_col.is_none() && args.min_cols.is_none()),
+                timestamp_format: args.timestamp_format,
+                timezone: args.timezone,
+            },
+            time_range,
+            station_id_filter: args.station_id_filter,
+            connector_id_filter: args.connector_id_filter,
+            transaction_id_filter: args.transaction_id_filter.clone(),
+            measurand_filter,
+            action_filter: args.action_filter,
+            log_all_messages: args.log_all_messages,
+            ack_timeout: chrono::TimeDelta::seconds(args.ack_timeout_s.into()),
+            validator: args
+                .validate_ocpp
+                .then(|| OcppValidator::load(args.ocpp_schema_dir.as_deref()))
+                .transpose()?,
+            sort: !args.no_sort,
+            normalize_units: !args.no_unit_normalize,
+            max_lines: args.max_lines,
+        };
+        let downloaded = download_files(&args.exclusive.trace_url).await?;
+        let (captured, failed_captures) =
+            read_captures(&args.exclusive.pcap, &args.pcap_port, args.max_file_size_mb);
+        let mut stations = Vec::new();
+        let mut meter_roots = Vec::new();
+        // Without sorting, each line can be plotted as soon as it is read.
+        let (trace_file_entries, mut stats, failed_trace_files) =
+            if args.no_sort && !args.dry_run && args.split_by_station.is_none() {
+                let mut parser = TraceParser::new(&parse_config);
+                let failed_trace_files = stream_trace_files(
+                    &trace_files,
+                    !stdin.is_empty(),
+                    &downloaded,
+                    captured,
+                    args.max_file_size_mb,
+                    &mut parser,
+                    |trace_file_entries| {
+                        update_blueprint(
+                            &rec,
+                            &layout,
+                            &mut stations,
+                            &mut meter_roots,
+                            trace_file_entries,
+                        )?;
+                        log_trace_file_entries(
+                            &rec,
+                            &mut trace_plotter,
+                            &mut station_streams,
+                            &mut exports,
+                            &mut session_report,
+                            &mut html_report,
+                            trace_file_entries,
+                        )
+                    },
+                )?;
+                (Vec::new(), parser.stats, failed_trace_files)
+            } else {
+                let (mut trace_contents, failed_trace_files) =
+                    read_files(&trace_files, args.max_file_size_mb).await;
+                if !stdin.is_empty() {
+                    trace_contents.extend(read_stdin().await?);
+                }
+                trace_contents.extend(downloaded);
+                let (trace_file_entries, stats) =
+                    parse_trace_lines(&trace_contents, captured, &parse_config)?;
+                (trace_file_entries, stats, failed_trace_files)
+            };
+        stats.files = trace_files.len() - failed_trace_files
+            + usize::from(!stdin.is_empty())
+            + args.exclusive.trace_url.len()
+            + (args.exclusive.pcap.len() - failed_captures);
+        stats.failed_files = failed_trace_files + failed_log_files + failed_captures;
+
+        if args.dry_run {
+            if !args.no_summary {
+                stats.print(args.display_timezone);
+            }
+            if let Some(session_report) = &mut session_report {
+                trace_file_entries
+                    .iter()
+                    .for_each(|trace_file_entry| session_report.add(trace_file_entry));
+                session_report.print()?;
+            }
+            if stats.errors() > 0 {
+                anyhow::bail!("{} trace lines failed to parse", stats.errors());
+            }
 
-fn main() -> anyhow::Result<()> {
-    re_log::setup_logging();
-    let args = Args::parse();
-    let rec = rerun::RecordingStreamBuilder::new("OcppMeter values")
-        .with_blueprint(setup_blueprint())
-        .spawn()?;
+            return Ok(());
+        }
 
-    if let Some(trace_file_directory) = args.exclusive.trace_file_directory {
-        let trace_contents: Vec<String> = read_file(&trace_file_directory, "trace");
-        let log_contents: Vec<String> = read_file(&trace_file_directory, "log");
+        if let Some(output_dir) = &args.split_by_station {
+            // Only writes the export files, with the station IDs that are split off below.
+            log_trace_file_entries(
+                &rec,
+                &mut trace_plotter,
+                &mut station_streams,
+                &mut exports,
+                &mut session_report,
+                &mut html_report,
+                &trace_file_entries,
+            )?;
+            if !args.no_summary {
+                stats.print(args.display_timezone);
+            }
+            split_by_station(
+                output_dir,
+                &layout,
+                &plot_config,
+                trace_file_entries,
+                !args.no_summary,
+            )?;
+            if let Some(session_report) = &session_report {
+                session_report.print()?;
+            }
+            if let (Some(html_report), Some(path)) = (&html_report, &args.report_html) {
+                html_report.write(path)?;
+            }
+            return exports.finish();
+        }
 
-        parse_meter_value_readings(&trace_contents)?
-            .iter()
-            .try_for_each(|trace_file_entry| plot_meter_value_readings(&rec, trace_file_entry))?;
+        update_blueprint(
+            &rec,
+            &layout,
+            &mut stations,
+            &mut meter_roots,
+            &trace_file_entries,
+        )?;
+
+        log_trace_file_entries(
+            &rec,
+            &mut trace_plotter,
+            &mut station_streams,
+            &mut exports,
+            &mut session_report,
+            &mut html_report,
+            &trace_file_entries,
+        )?;
+
+        if rec.is_enabled() {
+            parse_log_file_entries(&log_contents, &parse_config.time_range)?
+                .iter()
+                .try_for_each(|log_file_entry| {
+                    plot_log_file_entry(&rec, &layout.station(""), log_file_entry)
+                })?;
+        }
 
-        parse_log_file_entries(&log_contents)?
-            .iter()
-            .try_for_each(|log_file_entry| plot_log_file_entry(&rec, log_file_entry))?;
+        if !args.no_summary {
+            stats.print(args.display_timezone);
+        }
+
+        if args.follow {
+            let mut offsets = follow::FileOffsets::default();
+            for trace_file in &trace_files {
+                offsets.mark_read(trace_file).await?;
+            }
+            follow::follow(
+                &trace_file_directories,
+                &args.exclusive.trace_file,
+                &args.extension,
+                &mut offsets,
+                |lines| {
+                    let (trace_file_entries, _) = parse_trace_file_entries(&lines, &parse_config)?;
+                    update_blueprint(
+                        &rec,
+                        &layout,
+                        &mut stations,
+                        &mut meter_roots,
+                        &trace_file_entries,
+                    )?;
+
+                    log_trace_file_entries(
+                        &rec,
+                        &mut trace_plotter,
+                        &mut station_streams,
+                        &mut exports,
+                        &mut session_report,
+                        &mut html_report,
+                        &trace_file_entries,
+                    )
+                },
+            )
+            .await?;
+        }
+
+        trace_plotter.finish_sessions()?;
+        trace_plotter.finish_connectors()?;
+        match &mut station_streams {
+            Some(station_streams) => station_streams.finish(!args.no_summary)?,
+            None if !args.no_summary => trace_plotter.print_summary(),
+            None => {}
+        }
+        if let Some(session_report) = &session_report {
+            session_report.print()?;
+        }
+        if let (Some(html_report), Some(path)) = (&html_report, &args.report_html) {
+            html_report.write(path)?;
+        }
+        if let Some(path) = &args.blueprint_output {
+            save_blueprint(path, &layout, &stations, &meter_roots)?;
+        }
     } else if let Some(sqlite_db_path) = args.exclusive.sqlite_db_path {
         let connection = Connection::open(sqlite_db_path)?;
 
@@ -506,67 +1507,53 @@ fn main() -> anyhow::Result<()> {
                 row.get::<usize, String>(0)?,
                 row.get::<usize, i64>(1)?,
                 row.get::<usize, f64>(2)?,
-                if row.get::<usize, String>(3)? == "L1".to_string() {
-                    Some(Phase::L1)
-                } else if row.get::<usize, String>(3)? == "L2".to_string() {
-                    Some(Phase::L2)
-                } else if row.get::<usize, String>(3)? == "L3".to_string() {
-                    Some(Phase::L3)
-                } else if row.get::<usize, String>(3)? == "N".to_string() {
-                    Some(Phase::N)
-                } else if row.get::<usize, String>(3)? == "L1-N".to_string() {
-                    Some(Phase::L1N)
-                } else if row.get::<usize, String>(3)? == "L2-N".to_string() {
-                    Some(Phase::L2N)
-                } else if row.get::<usize, String>(3)? == "L3-N".to_string() {
-                    Some(Phase::L3N)
-                } else if row.get::<usize, String>(3)? == "L1-L2".to_string() {
-                    Some(Phase::L1L2)
-                } else if row.get::<usize, String>(3)? == "L2-L3".to_string() {
-                    Some(Phase::L2L3)
-                } else if row.get::<usize, String>(3)? == "L3-L1".to_string() {
-                    Some(Phase::L3L1)
-                } else {
-                    None
-                },
+                row.get::<usize, Option<String>>(3)?,
             );
 
-            rec.set_timestamp_secs_since_epoch(
-                "time",
-                chrono::Duration::milliseconds(timestamp).as_seconds_f64(),
-            );
+            if !time_range.contains_millis(timestamp) {
+                continue;
+            }
 
-            if name == "CurrentImport"
-                && let Some(phase) = phase
-            {
-                plot_current_import(&rec, phase, value)?;
-            } else if name == "CurrentOffered" {
-                plot_current_offered(&rec, value)?;
-            } else if name == "PowerActiveImport"
-                && let Some(phase) = phase
-            {
-                plot_power_active_import(&rec, phase, value)?;
-            } else if name == "PowerOffered" {
-                plot_power_offered(&rec, value)?;
-            } else if name == "Voltage"
-                && let Some(phase) = phase
-            {
-                plot_voltage(&rec, phase, value)?;
+            let Some(measurand) = measurand_from_name(name.as_str())
+                .filter(|measurand| measurand_filter.matches(measurand))
+            else {
+                continue;
+            };
+
+            let reading = SampledReading {
+                measurand,
+                phase: phase.and_then(|phase| {
+                    serde_json::from_value::<Phase>(serde_json::Value::String(phase)).ok()
+                }),
+                unit: None,
+                value,
+            };
+            if rec.is_enabled() {
+                let timestamp_s = chrono::Duration::milliseconds(timestamp).as_seconds_f64();
+                trace_plotter.set_time(timestamp_s);
+                trace_plotter.plot_sampled_reading(&layout.station(""), timestamp_s, &reading)?;
+            }
+
+            if let Some(timestamp) = DateTime::from_timestamp_millis(timestamp) {
+                exports.write_reading(&timestamp.fixed_offset(), &reading)?;
             }
         }
 
+        // The sum is only plotted, there is no sampled value to export for it.
         let mut power_active_import_sum_stmt =
             connection.prepare("SELECT timestamp, SUM(value) FROM meter_readings WHERE name = 'PowerActiveImport' GROUP BY timestamp;")?;
         let mut power_active_import_sum = power_active_import_sum_stmt.query([])?;
 
-        while let Some(row) = power_active_import_sum.next()? {
+        while rec.is_enabled()
+            && let Some(row) = power_active_import_sum.next()?
+        {
             let (timestamp, value) = (row.get::<usize, i64>(0)?, row.get::<usize, f64>(1)?);
-            rec.set_timestamp_secs_since_epoch(
-                "time",
-                chrono::Duration::milliseconds(timestamp).as_seconds_f64(),
-            );
+            if !time_range.contains_millis(timestamp) {
+                continue;
+            }
 
-            plot_power_active_import_sum(&rec, value)?;
+            trace_plotter.set_time(chrono::Duration::milliseconds(timestamp).as_seconds_f64());
+            trace_plotter.plot_power_active_import_sum(&layout.station(""), value)?;
         }
 
         /*
@@ -586,5 +1573,5 @@ fn main() -> anyhow::Result<()> {
         */
     }
 
-    Ok(())
+    exports.finish()
 }
@@ -0,0 +1,730 @@
+//! OCPP messages decoded from the frames of the trace lines, and the measurands and phases of
+//! their sampled values.
+
+use chrono::{DateTime, FixedOffset};
+use itertools::Itertools;
+use rerun::external::re_log;
+use rust_decimal::prelude::ToPrimitive;
+use rust_ocpp::v1_6::{
+    messages::{
+        boot_notification::BootNotificationRequest,
+        change_configuration::{ChangeConfigurationRequest, ChangeConfigurationResponse},
+        diagnostics_status_notification::DiagnosticsStatusNotificationRequest,
+        firmware_status_notification::FirmwareStatusNotificationRequest,
+        get_configuration::{GetConfigurationRequest, GetConfigurationResponse},
+        meter_values::MeterValuesRequest,
+        remote_start_transaction::{RemoteStartTransactionRequest, RemoteStartTransactionResponse},
+        remote_stop_transaction::{RemoteStopTransactionRequest, RemoteStopTransactionResponse},
+        reset::{ResetRequest, ResetResponse},
+        start_transaction::{StartTransactionRequest, StartTransactionResponse},
+        status_notification::StatusNotificationRequest,
+        stop_transaction::{StopTransactionRequest, StopTransactionResponse},
+        trigger_message::{TriggerMessageRequest, TriggerMessageResponse},
+        unlock_connector::{UnlockConnectorRequest, UnlockConnectorResponse},
+    },
+    types::{
+        ConfigurationStatus, KeyValue, Measurand, MessageTrigger, Phase, RemoteStartStopStatus,
+        ResetRequestStatus, ResetResponseStatus, TriggerMessageStatus, UnitOfMeasure, UnlockStatus,
+    },
+};
+use rust_ocpp::v2_0_1::{
+    self, datatypes::meter_value_type::MeterValueType,
+    messages::transaction_event::TransactionEventRequest,
+};
+use serde::{Deserialize, de::DeserializeOwned};
+
+/// OCPP wire name of an enum value, e.g. `Current.Import` for `Measurand::CurrentImport`.
+pub fn ocpp_name<T: serde::Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// Parses the variant name of a measurand, e.g. `CurrentImport`, ignoring case.
+pub fn parse_measurand_name(name: &str) -> Result<Measurand, String> {
+    MEASURANDS
+        .iter()
+        .find(|measurand| format!("{:?}", measurand).eq_ignore_ascii_case(name))
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "expected one of {}",
+                MEASURANDS
+                    .iter()
+                    .map(|measurand| format!("{:?}", measurand))
+                    .join(", ")
+            )
+        })
+}
+
+pub const MEASURANDS: [Measurand; 22] = [
+    Measurand::CurrentExport,
+    Measurand::CurrentImport,
+    Measurand::CurrentOffered,
+    Measurand::EnergyActiveExportRegister,
+    Measurand::EnergyActiveImportRegister,
+    Measurand::EnergyReactiveExportRegister,
+    Measurand::EnergyReactiveImportRegister,
+    Measurand::EnergyActiveExportInterval,
+    Measurand::EnergyActiveImportInterval,
+    Measurand::EnergyReactiveExportInterval,
+    Measurand::EnergyReactiveImportInterval,
+    Measurand::Frequency,
+    Measurand::PowerActiveExport,
+    Measurand::PowerActiveImport,
+    Measurand::PowerFactor,
+    Measurand::PowerOffered,
+    Measurand::PowerReactiveExport,
+    Measurand::PowerReactiveImport,
+    Measurand::Rpm,
+    Measurand::SoC,
+    Measurand::Temperature,
+    Measurand::Voltage,
+];
+
+/// Description and default unit of a measurand for `--list-measurands`.
+pub fn measurand_description(measurand: &Measurand) -> (&'static str, &'static str) {
+    match measurand {
+        Measurand::CurrentExport => ("Instantaneous current flow from EV", "A"),
+        Measurand::CurrentImport => ("Instantaneous current flow to EV", "A"),
+        Measurand::CurrentOffered => ("Maximum current offered to EV", "A"),
+        Measurand::EnergyActiveExportRegister => (
+            "Energy exported by EV, numerical value read from the meter",
+            "Wh",
+        ),
+        Measurand::EnergyActiveImportRegister => (
+            "Energy imported by EV, numerical value read from the meter",
+            "Wh",
+        ),
+        Measurand::EnergyReactiveExportRegister => (
+            "Reactive energy exported by EV, numerical value read from the meter",
+            "varh",
+        ),
+        Measurand::EnergyReactiveImportRegister => (
+            "Reactive energy imported by EV, numerical value read from the meter",
+            "varh",
+        ),
+        Measurand::EnergyActiveExportInterval => {
+            ("Energy exported by EV during the last interval", "Wh")
+        }
+        Measurand::EnergyActiveImportInterval => {
+            ("Energy imported by EV during the last interval", "Wh")
+        }
+        Measurand::EnergyReactiveExportInterval => (
+            "Reactive energy exported by EV during the last interval",
+            "varh",
+        ),
+        Measurand::EnergyReactiveImportInterval => (
+            "Reactive energy imported by EV during the last interval",
+            "varh",
+        ),
+        Measurand::Frequency => ("Instantaneous reading of the powerline frequency", "Hz"),
+        Measurand::PowerActiveExport => ("Instantaneous active power exported by EV", "W"),
+        Measurand::PowerActiveImport => ("Instantaneous active power imported by EV", "W"),
+        Measurand::PowerFactor => ("Instantaneous power factor of the total energy flow", "-"),
+        Measurand::PowerOffered => ("Maximum power offered to EV", "W"),
+        Measurand::PowerReactiveExport => ("Instantaneous reactive power exported by EV", "var"),
+        Measurand::PowerReactiveImport => ("Instantaneous reactive power imported by EV", "var"),
+        Measurand::Rpm => ("Fan speed", "RPM"),
+        Measurand::SoC => ("State of charge of the EV battery", "Percent"),
+        Measurand::Temperature => ("Temperature reading inside the charge point", "Celsius"),
+        Measurand::Voltage => ("Instantaneous AC RMS supply voltage", "V"),
+    }
+}
+
+pub const PHASES: [Phase; 10] = [
+    Phase::L1,
+    Phase::L2,
+    Phase::L3,
+    Phase::N,
+    Phase::L1N,
+    Phase::L2N,
+    Phase::L3N,
+    Phase::L1L2,
+    Phase::L2L3,
+    Phase::L3L1,
+];
+
+/// Description of a phase for `--list-phases`.
+pub fn phase_description(phase: &Phase) -> &'static str {
+    match phase {
+        Phase::L1 => "Measured on L1",
+        Phase::L2 => "Measured on L2",
+        Phase::L3 => "Measured on L3",
+        Phase::N => "Measured on Neutral",
+        Phase::L1N => "Measured on L1 with respect to Neutral conductor",
+        Phase::L2N => "Measured on L2 with respect to Neutral conductor",
+        Phase::L3N => "Measured on L3 with respect to Neutral conductor",
+        Phase::L1L2 => "Measured between L1 and L2",
+        Phase::L2L3 => "Measured between L2 and L3",
+        Phase::L3L1 => "Measured between L3 and L1",
+    }
+}
+
+/// Looks up a measurand by its variant name, e.g. `CurrentImport`.
+pub fn measurand_from_name(name: &str) -> Option<Measurand> {
+    MEASURANDS
+        .iter()
+        .find(|measurand| format!("{:?}", measurand) == name)
+        .cloned()
+}
+
+pub const LINE_PHASES: [Phase; 3] = [Phase::L1, Phase::L2, Phase::L3];
+
+/// Value of the first reading of `measurand` on `phase`, if any.
+pub fn phase_value(
+    readings: &[SampledReading],
+    measurand: &Measurand,
+    phase: &Phase,
+) -> Option<f64> {
+    readings
+        .iter()
+        .find(|reading| &reading.measurand == measurand && reading.phase.as_ref() == Some(phase))
+        .map(|reading| reading.value)
+}
+
+/// Total import power, the sum of the line phases, or the reading without a phase of chargers
+/// that only report the total.
+pub fn total_power_active_import(readings: &[SampledReading]) -> Option<f64> {
+    let phases = readings
+        .iter()
+        .filter(|reading| {
+            reading.measurand == Measurand::PowerActiveImport
+                && matches!(reading.phase, Some(Phase::L1 | Phase::L2 | Phase::L3))
+        })
+        .map(|reading| reading.value)
+        .collect::<Vec<_>>();
+
+    if phases.is_empty() {
+        readings
+            .iter()
+            .find(|reading| {
+                reading.measurand == Measurand::PowerActiveImport && reading.phase.is_none()
+            })
+            .map(|reading| reading.value)
+    } else {
+        Some(phases.iter().sum())
+    }
+}
+
+pub struct SampledReading {
+    pub measurand: Measurand,
+    pub phase: Option<Phase>,
+    pub unit: Option<UnitOfMeasure>,
+    pub value: f64,
+}
+
+impl SampledReading {
+    /// Converts kilo units to their base unit, so readings of chargers reporting in different
+    /// units end up on the same scale.
+    pub fn normalize_unit(&mut self) {
+        let base_unit = match self.unit {
+            Some(UnitOfMeasure::KWh) => UnitOfMeasure::Wh,
+            Some(UnitOfMeasure::Kvarh) => UnitOfMeasure::Varh,
+            Some(UnitOfMeasure::Kw) => UnitOfMeasure::W,
+            Some(UnitOfMeasure::Kva) => UnitOfMeasure::Va,
+            Some(UnitOfMeasure::Kvar) => UnitOfMeasure::Var,
+            _ => return,
+        };
+
+        self.value *= 1000.0;
+        self.unit = Some(base_unit);
+    }
+}
+
+/// Sampled values of one MeterValues message, or of the meter values of a TransactionEvent.
+pub struct MeterValuesBatch {
+    /// OCPP 1.6 connectorId, or the EVSE ID for OCPP 2.0.1 messages.
+    pub connector_id: Option<u32>,
+    /// Transaction IDs are integers in OCPP 1.6 and strings in OCPP 2.0.1.
+    pub transaction_id: Option<String>,
+    pub readings: Vec<SampledReading>,
+    /// Measurands of the sampled values that were skipped because their value isn't a finite
+    /// number.
+    pub invalid_readings: Vec<Measurand>,
+}
+
+pub enum OcppMessage {
+    MeterValues(MeterValuesBatch),
+    StartTransaction(StartTransactionRequest),
+    /// StartTransaction call answered with the ID of the transaction.
+    StartTransactionResponse {
+        request: StartTransactionRequest,
+        response: StartTransactionResponse,
+    },
+    StopTransaction(StopTransactionRequest),
+    StopTransactionResponse(StopTransactionResponse),
+    StatusNotification(StatusNotificationRequest),
+    Heartbeat,
+    BootNotification(BootNotificationRequest),
+    FirmwareStatusNotification(FirmwareStatusNotificationRequest),
+    DiagnosticsStatusNotification(DiagnosticsStatusNotificationRequest),
+    /// ChangeConfiguration call answered with the status of the change.
+    ConfigurationChange {
+        key: String,
+        value: String,
+        status: ConfigurationStatus,
+    },
+    /// Keys reported in the result of a GetConfiguration call.
+    Configuration(Vec<KeyValue>),
+    /// Reset call answered with whether the station is going to reset.
+    Reset {
+        kind: ResetRequestStatus,
+        status: ResetResponseStatus,
+    },
+    /// RemoteStartTransaction call answered with whether the station is going to start it.
+    RemoteStartTransaction {
+        id_tag: String,
+        connector_id: Option<u32>,
+        charging_profile_id: Option<i32>,
+        status: RemoteStartStopStatus,
+    },
+    /// RemoteStopTransaction call answered with whether the station is going to stop it.
+    RemoteStopTransaction {
+        transaction_id: i32,
+        status: RemoteStartStopStatus,
+    },
+    /// UnlockConnector call answered with whether the connector was unlocked.
+    UnlockConnector {
+        connector_id: u32,
+        status: UnlockStatus,
+    },
+    /// TriggerMessage call answered with whether the station is going to send the message.
+    TriggerMessage {
+        requested_message: MessageTrigger,
+        connector_id: Option<u32>,
+        status: TriggerMessageStatus,
+    },
+    /// Time between a call and its CallResult or CallError.
+    CallLatency {
+        action: String,
+        latency_ms: f64,
+    },
+    /// CallError answering a call of `action`, `Unknown` if the call wasn't traced.
+    CallError {
+        action: String,
+        error_code: String,
+        error_description: String,
+    },
+    /// CallResult of a call of `action`, confirming that it was received.
+    CallAck {
+        action: String,
+    },
+    /// Call of `action` that got no CallResult or CallError within the timeout.
+    CallTimeout {
+        action: String,
+        unique_id: String,
+        timeout_s: f64,
+    },
+    /// Ways the payload of a call, or of the result of `{action}Response`, violates its schema.
+    SchemaViolation {
+        action: String,
+        violations: Vec<String>,
+    },
+    /// Numeric fields of the vendor-specific data.
+    DataTransfer {
+        vendor_id: String,
+        fields: Vec<(String, f64)>,
+    },
+    /// JSON column of a trace line, logged as is with `--log-all-messages`.
+    Raw {
+        action: String,
+        json: String,
+    },
+}
+
+impl OcppMessage {
+    /// Whether the message refers to one of `connector_ids`. Messages without a connector always
+    /// match, except for meter values, which belong to a connector.
+    pub fn matches_connectors(&self, connector_ids: &[u32]) -> bool {
+        let connector_id = match self {
+            OcppMessage::MeterValues(meter_values) => meter_values.connector_id,
+            OcppMessage::StartTransaction(request) => Some(request.connector_id),
+            OcppMessage::StatusNotification(request) => Some(request.connector_id),
+            OcppMessage::UnlockConnector { connector_id, .. } => Some(*connector_id),
+            _ => return true,
+        };
+
+        connector_ids.is_empty()
+            || connector_id.is_some_and(|connector_id| connector_ids.contains(&connector_id))
+    }
+
+    /// Whether the message refers to one of `transaction_ids`, like
+    /// [`OcppMessage::matches_connectors`].
+    pub fn matches_transactions(&self, transaction_ids: &[String]) -> bool {
+        let transaction_id = match self {
+            OcppMessage::MeterValues(meter_values) => meter_values.transaction_id.clone(),
+            OcppMessage::StartTransactionResponse { response, .. } => {
+                Some(response.transaction_id.to_string())
+            }
+            OcppMessage::StopTransaction(request) => Some(request.transaction_id.to_string()),
+            OcppMessage::RemoteStopTransaction { transaction_id, .. } => {
+                Some(transaction_id.to_string())
+            }
+            _ => return true,
+        };
+
+        transaction_ids.is_empty()
+            || transaction_id
+                .is_some_and(|transaction_id| transaction_ids.contains(&transaction_id))
+    }
+}
+
+pub struct TraceFileEntry {
+    pub timestamp: DateTime<FixedOffset>,
+    pub station_id: Option<String>,
+    pub message: OcppMessage,
+}
+
+pub fn meter_values(meter_values_request: &MeterValuesRequest) -> MeterValuesBatch {
+    let mut readings: Vec<SampledReading> = Vec::new();
+    let mut invalid_readings: Vec<Measurand> = Vec::new();
+
+    for meter_value in &meter_values_request.meter_value {
+        for sampled_value in &meter_value.sampled_value {
+            // OCPP 1.6 defines Energy.Active.Import.Register as the default measurand.
+            let measurand = sampled_value.measurand.clone().unwrap_or_default();
+
+            // NaN and infinity parse fine but would break the time series just like a fallback.
+            let Some(value) = sampled_value
+                .value
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+            else {
+                re_log::warn!(
+                    "Skipping {:?} reading with invalid value `{}`",
+                    measurand,
+                    sampled_value.value
+                );
+                invalid_readings.push(measurand);
+                continue;
+            };
+
+            readings.push(SampledReading {
+                measurand,
+                phase: sampled_value.phase.clone(),
+                unit: sampled_value.unit.clone(),
+                value,
+            });
+        }
+    }
+
+    MeterValuesBatch {
+        connector_id: Some(meter_values_request.connector_id),
+        transaction_id: meter_values_request
+            .transaction_id
+            .map(|transaction_id| transaction_id.to_string()),
+        readings,
+        invalid_readings,
+    }
+}
+
+/// OCPP 2.0.1 sampled values are mapped onto their OCPP 1.6 counterparts so both end up on the
+/// same entity paths. Measurands that only exist in 2.0.1 (e.g. `Energy.Active.Net`) are skipped,
+/// and so are values that don't convert to a float, which are returned as the invalid readings.
+pub fn meter_value_type_readings(
+    meter_values: &[MeterValueType],
+) -> (Vec<SampledReading>, Vec<Measurand>) {
+    let mut readings: Vec<SampledReading> = Vec::new();
+    let mut invalid_readings: Vec<Measurand> = Vec::new();
+
+    for meter_value in meter_values {
+        for sampled_value in &meter_value.sampled_value {
+            let measurand = match &sampled_value.measurand {
+                Some(measurand) => match serde_json::to_value(measurand)
+                    .and_then(serde_json::from_value::<Measurand>)
+                {
+                    Ok(measurand) => measurand,
+                    Err(_) => continue,
+                },
+                None => Measurand::default(),
+            };
+            let Some(value) = sampled_value
+                .value
+                .to_f64()
+                .filter(|value| value.is_finite())
+            else {
+                re_log::warn!(
+                    "Skipping {:?} reading with invalid value `{}`",
+                    measurand,
+                    sampled_value.value
+                );
+                invalid_readings.push(measurand);
+                continue;
+            };
+
+            readings.push(SampledReading {
+                measurand,
+                phase: sampled_value.phase.as_ref().and_then(|phase| {
+                    serde_json::to_value(phase)
+                        .and_then(serde_json::from_value::<Phase>)
+                        .ok()
+                }),
+                unit: sampled_value
+                    .unit_of_measure
+                    .as_ref()
+                    .and_then(|unit_of_measure| unit_of_measure.unit.clone())
+                    .and_then(|unit| {
+                        serde_json::from_value::<UnitOfMeasure>(serde_json::Value::String(unit))
+                            .ok()
+                    }),
+                value,
+            });
+        }
+    }
+
+    (readings, invalid_readings)
+}
+
+/// Why a call couldn't be turned into an [`OcppMessage`].
+pub enum DecodeError {
+    UnknownAction,
+    Payload(serde_json::Error),
+}
+
+impl From<serde_json::Error> for DecodeError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Payload(error)
+    }
+}
+
+/// Decodes the payload of a call, or returns `None` for calls that are valid but carry nothing to
+/// plot.
+pub fn decode_call(
+    action: &str,
+    payload: &serde_json::Value,
+) -> Result<Option<OcppMessage>, DecodeError> {
+    let message = match action {
+        "MeterValues" => OcppMessage::MeterValues(
+            MeterValuesRequest::deserialize(payload)
+                .map(|request| meter_values(&request))
+                .or_else(|_| {
+                    v2_0_1::messages::meter_values::MeterValuesRequest::deserialize(payload).map(
+                        |request| {
+                            let (readings, invalid_readings) =
+                                meter_value_type_readings(&request.meter_value);
+                            MeterValuesBatch {
+                                connector_id: u32::try_from(request.evse_id).ok(),
+                                transaction_id: None,
+                                readings,
+                                invalid_readings,
+                            }
+                        },
+                    )
+                })?,
+        ),
+        "TransactionEvent" => {
+            let request = TransactionEventRequest::deserialize(payload)?;
+            let Some(meter_value) = request.meter_value else {
+                return Ok(None);
+            };
+
+            let (readings, invalid_readings) = meter_value_type_readings(&meter_value);
+            OcppMessage::MeterValues(MeterValuesBatch {
+                connector_id: request.evse.and_then(|evse| u32::try_from(evse.id).ok()),
+                transaction_id: Some(request.transaction_info.transaction_id),
+                readings,
+                invalid_readings,
+            })
+        }
+        "StartTransaction" => OcppMessage::StartTransaction(Deserialize::deserialize(payload)?),
+        "StopTransaction" => OcppMessage::StopTransaction(Deserialize::deserialize(payload)?),
+        "StatusNotification" => OcppMessage::StatusNotification(Deserialize::deserialize(payload)?),
+        "Heartbeat" => OcppMessage::Heartbeat,
+        "BootNotification" => OcppMessage::BootNotification(Deserialize::deserialize(payload)?),
+        "FirmwareStatusNotification" => {
+            OcppMessage::FirmwareStatusNotification(Deserialize::deserialize(payload)?)
+        }
+        "DiagnosticsStatusNotification" => {
+            OcppMessage::DiagnosticsStatusNotification(Deserialize::deserialize(payload)?)
+        }
+        // Only logged once they are answered, see `decode_call_result`.
+        "ChangeConfiguration" => {
+            ChangeConfigurationRequest::deserialize(payload)?;
+            return Ok(None);
+        }
+        "GetConfiguration" => {
+            GetConfigurationRequest::deserialize(payload)?;
+            return Ok(None);
+        }
+        "Reset" => {
+            ResetRequest::deserialize(payload)?;
+            return Ok(None);
+        }
+        "TriggerMessage" => {
+            TriggerMessageRequest::deserialize(payload)?;
+            return Ok(None);
+        }
+        "UnlockConnector" => {
+            UnlockConnectorRequest::deserialize(payload)?;
+            return Ok(None);
+        }
+        "RemoteStartTransaction" => {
+            RemoteStartTransactionRequest::deserialize(payload)?;
+            return Ok(None);
+        }
+        "RemoteStopTransaction" => {
+            RemoteStopTransactionRequest::deserialize(payload)?;
+            return Ok(None);
+        }
+        "DataTransfer" => {
+            let request = DataTransferPayload::deserialize(payload)?;
+            OcppMessage::DataTransfer {
+                fields: request
+                    .data
+                    .as_ref()
+                    .map(data_transfer_fields)
+                    .unwrap_or_default(),
+                vendor_id: request.vendor_id,
+            }
+        }
+        _ => return Err(DecodeError::UnknownAction),
+    };
+
+    Ok(Some(message))
+}
+
+/// DataTransfer request whose `data` may be a JSON object instead of the string the spec demands.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataTransferPayload {
+    pub vendor_id: String,
+    pub data: Option<serde_json::Value>,
+}
+
+/// Numeric fields of vendor data, given as JSON object or as string holding one. Numbers in
+/// strings count as well.
+pub fn data_transfer_fields(data: &serde_json::Value) -> Vec<(String, f64)> {
+    let object = match data {
+        serde_json::Value::String(data) => serde_json::from_str::<serde_json::Value>(data).ok(),
+        data => Some(data.clone()),
+    };
+    let Some(serde_json::Value::Object(object)) = object else {
+        return Vec::new();
+    };
+
+    object
+        .into_iter()
+        .filter_map(|(field, value)| {
+            let value = match value {
+                serde_json::Value::Number(value) => value.as_f64(),
+                serde_json::Value::String(value) => value.parse::<f64>().ok(),
+                _ => None,
+            }?;
+            Some((field, value)).filter(|_| value.is_finite())
+        })
+        .collect()
+}
+
+/// Decodes the payload of the result of a call of `action`, with the payload of the call for
+/// results that only make sense together with their request.
+pub fn decode_call_result(
+    action: &str,
+    request: &serde_json::Value,
+    payload: serde_json::Value,
+) -> Option<OcppMessage> {
+    match action {
+        "ChangeConfiguration" => {
+            let (request, response) = answered::<
+                ChangeConfigurationRequest,
+                ChangeConfigurationResponse,
+            >(request, payload)?;
+            Some(OcppMessage::ConfigurationChange {
+                key: request.key,
+                value: request.value,
+                status: response.status,
+            })
+        }
+        "Reset" => {
+            let (request, response) = answered::<ResetRequest, ResetResponse>(request, payload)?;
+            Some(OcppMessage::Reset {
+                kind: request.kind,
+                status: response.status,
+            })
+        }
+        "TriggerMessage" => {
+            let (request, response) =
+                answered::<TriggerMessageRequest, TriggerMessageResponse>(request, payload)?;
+            Some(OcppMessage::TriggerMessage {
+                requested_message: request.requested_message,
+                connector_id: request.connector_id,
+                status: response.status,
+            })
+        }
+        "UnlockConnector" => {
+            let (request, response) =
+                answered::<UnlockConnectorRequest, UnlockConnectorResponse>(request, payload)?;
+            Some(OcppMessage::UnlockConnector {
+                connector_id: request.connector_id,
+                status: response.status,
+            })
+        }
+        "RemoteStartTransaction" => {
+            let (request, response) = answered::<
+                RemoteStartTransactionRequest,
+                RemoteStartTransactionResponse,
+            >(request, payload)?;
+            Some(OcppMessage::RemoteStartTransaction {
+                id_tag: request.id_tag,
+                connector_id: request.connector_id,
+                charging_profile_id: request
+                    .charging_profile
+                    .map(|charging_profile| charging_profile.charging_profile_id),
+                status: response.status,
+            })
+        }
+        "RemoteStopTransaction" => {
+            let (request, response) = answered::<
+                RemoteStopTransactionRequest,
+                RemoteStopTransactionResponse,
+            >(request, payload)?;
+            Some(OcppMessage::RemoteStopTransaction {
+                transaction_id: request.transaction_id,
+                status: response.status,
+            })
+        }
+        "GetConfiguration" => serde_json::from_value::<GetConfigurationResponse>(payload)
+            .ok()
+            .map(|response| {
+                OcppMessage::Configuration(response.configuration_key.unwrap_or_default())
+            }),
+        "StartTransaction" => {
+            let (request, response) =
+                answered::<StartTransactionRequest, StartTransactionResponse>(request, payload)?;
+            Some(OcppMessage::StartTransactionResponse { request, response })
+        }
+        "StopTransaction" => serde_json::from_value(payload)
+            .ok()
+            .map(OcppMessage::StopTransactionResponse),
+        _ => None,
+    }
+}
+
+/// Request of a call together with the payload of its result.
+fn answered<Request: DeserializeOwned, Response: DeserializeOwned>(
+    request: &serde_json::Value,
+    payload: serde_json::Value,
+) -> Option<(Request, Response)> {
+    Some((
+        Deserialize::deserialize(request).ok()?,
+        serde_json::from_value(payload).ok()?,
+    ))
+}
+
+/// Without an envelope the action is unknown, so only request payloads with a distinctive shape
+/// are recognized, as one of the `actions` if any are given.
+pub fn decode_payload(payload: serde_json::Value, actions: &[String]) -> Option<OcppMessage> {
+    [
+        "MeterValues",
+        "TransactionEvent",
+        "StartTransaction",
+        "StopTransaction",
+        "StatusNotification",
+        "BootNotification",
+    ]
+    .iter()
+    .filter(|action| actions.is_empty() || actions.iter().any(|filter| filter == *action))
+    .find_map(|action| decode_call(action, &payload).ok().flatten())
+}
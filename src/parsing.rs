@@ -0,0 +1,1321 @@
+//! Splitting of trace lines into their columns and parsing of the timestamp and OCPP-J frame.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use indicatif::{ProgressBar, ProgressIterator};
+use itertools::Itertools;
+use rerun::external::re_log;
+use rust_ocpp::v1_6::types::Measurand;
+
+use crate::ocpp::{
+    DecodeError, MeterValuesBatch, OcppMessage, SampledReading, TraceFileEntry, decode_call,
+    decode_call_result, decode_payload, ocpp_name,
+};
+use crate::progress_bar;
+use crate::soap::{is_soap, parse_soap_frame};
+use crate::validation::OcppValidator;
+
+/// Removes the UTF-8 byte order mark some Windows tools write at the start of a file, which would
+/// otherwise end up in the date column of the first line.
+pub fn strip_bom(s: &str) -> &str {
+    s.strip_prefix('\u{feff}').unwrap_or(s)
+}
+
+/// Parses the date and time columns of a trace line as UTC `%Y-%m-%d %H:%M:%S%.f`, and falls back
+/// to an RFC 3339 timestamp in the date column.
+pub fn parse_log_timestamp(date: &str, time: &str) -> Option<DateTime<FixedOffset>> {
+    parse_trace_timestamp(date, time, None, Tz::UTC)
+}
+
+/// Like [`parse_log_timestamp`], with `format` replacing the default format of the joined date and
+/// time columns. Timestamps without an offset are local time of `timezone`, and the ones that
+/// don't exist there because of a DST transition are rejected. The result is in UTC.
+pub fn parse_trace_timestamp(
+    date: &str,
+    time: &str,
+    format: Option<&str>,
+    timezone: Tz,
+) -> Option<DateTime<FixedOffset>> {
+    let date = date.replace("[", "");
+    let date_time = format!("{} {}", date, time);
+    let format = format.unwrap_or("%Y-%m-%d %H:%M:%S%.f");
+
+    DateTime::parse_from_str(&date_time, format)
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(&date_time, format)
+                .ok()
+                // The first of the two instants of a local time repeated when DST ends.
+                .and_then(|naive| timezone.from_local_datetime(&naive).earliest())
+                .map(|timestamp| timestamp.fixed_offset())
+        })
+        .or_else(|| DateTime::parse_from_rfc3339(&date).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc).fixed_offset())
+}
+
+/// Columns of the whitespace-split trace lines, zero-based.
+pub struct LineFormat {
+    pub date_column: usize,
+    pub time_column: usize,
+    pub json_column: usize,
+    /// Splits the columns at exactly this character instead of at any whitespace.
+    pub delimiter: Option<char>,
+    /// The JSON is everything from the JSON column to the end of the line, for frames with
+    /// whitespace in them, instead of only that column.
+    pub json_col_last: bool,
+    /// Column holding the charge-point ID, if any.
+    pub station_id_column: Option<usize>,
+    /// Lines with fewer columns are skipped.
+    pub min_columns: usize,
+    /// Column holding the JSON of the lines with one column fewer than `min_columns`, which are
+    /// skipped too without it.
+    pub short_json_column: Option<usize>,
+    /// Replaces the default format of the joined date and time columns.
+    pub timestamp_format: Option<String>,
+    /// Timezone of the timestamps without an offset.
+    pub timezone: Tz,
+}
+
+impl Default for LineFormat {
+    /// `2024-01-15 08:23:10 INFO ws EVSE-001 -> CP Call MeterValues [2, ...]`, with the JSON in the
+    /// tenth column, or in the ninth one of the traces with a column fewer.
+    fn default() -> Self {
+        Self {
+            date_column: 0,
+            time_column: 1,
+            json_column: 9,
+            delimiter: None,
+            json_col_last: true,
+            station_id_column: None,
+            min_columns: 10,
+            short_json_column: Some(8),
+            timestamp_format: None,
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+/// The columns of a trace line the entries are built from.
+pub struct TraceLine {
+    pub timestamp: DateTime<FixedOffset>,
+    pub station_id: Option<String>,
+    pub json: String,
+}
+
+pub enum SkipReason {
+    TooFewColumns,
+    BadTimestamp,
+}
+
+/// Splits `line` into its columns, unless it doesn't have the layout of `format`.
+pub fn split_trace_line(line: &str, format: &LineFormat) -> Result<TraceLine, SkipReason> {
+    // Files with Windows line endings leave a `\r` at the end of every line.
+    let line = line.trim_end_matches(['\r', '\n']);
+    let line_parts: Vec<&str> = match format.delimiter {
+        Some(delimiter) => line.split(delimiter).collect(),
+        None => line.split(char::is_whitespace).collect(),
+    };
+    let json_column = match format.short_json_column {
+        Some(column) if line_parts.len() + 1 == format.min_columns => column,
+        _ if line_parts.len() < format.min_columns => {
+            re_log::debug!("Skipping trace line with too few columns: {}", line);
+            return Err(SkipReason::TooFewColumns);
+        }
+        _ => format.json_column,
+    };
+    let rest = match format.delimiter {
+        Some(delimiter) => line.splitn(json_column + 1, delimiter).nth(json_column),
+        None => line
+            .splitn(json_column + 1, char::is_whitespace)
+            .nth(json_column),
+    };
+
+    let json = if format.json_col_last {
+        rest.map(str::trim_end)
+    } else {
+        line_parts.get(json_column).copied()
+    };
+    let (Some(date), Some(time), Some(json)) = (
+        line_parts.get(format.date_column),
+        line_parts.get(format.time_column),
+        json,
+    ) else {
+        re_log::debug!("Skipping trace line with too few columns: {}", line);
+        return Err(SkipReason::TooFewColumns);
+    };
+
+    let Some(timestamp) = parse_trace_timestamp(
+        date,
+        time,
+        format.timestamp_format.as_deref(),
+        format.timezone,
+    ) else {
+        re_log::debug!("Skipping trace line with unparsable timestamp: {}", line);
+        return Err(SkipReason::BadTimestamp);
+    };
+
+    let station_id = format
+        .station_id_column
+        .and_then(|column| line_parts.get(column))
+        .map(|station_id| station_id.to_string());
+
+    Ok(TraceLine {
+        timestamp,
+        station_id,
+        json: json.to_string(),
+    })
+}
+
+/// OCPP-J frame found in the JSON column of a trace line, or the frame of the SOAP envelope found
+/// there instead.
+pub enum OcppFrame {
+    /// `[2, "<uniqueId>", "<action>", {<payload>}]`
+    Call {
+        unique_id: String,
+        action: String,
+        payload: serde_json::Value,
+    },
+    /// `[3, "<uniqueId>", {<payload>}]`
+    CallResult {
+        unique_id: String,
+        payload: serde_json::Value,
+    },
+    /// `[4, "<uniqueId>", "<errorCode>", "<errorDescription>", {<errorDetails>}]`
+    CallError {
+        unique_id: String,
+        error_code: String,
+        error_description: String,
+    },
+    /// Payload that was traced without the surrounding envelope.
+    Payload(serde_json::Value),
+}
+
+pub fn parse_ocpp_frame(json: &str) -> Option<OcppFrame> {
+    if is_soap(json) {
+        return parse_soap_frame(json);
+    }
+
+    let value = serde_json::from_str::<serde_json::Value>(json).ok()?;
+    let serde_json::Value::Array(mut frame) = value else {
+        return Some(OcppFrame::Payload(value));
+    };
+
+    let message_type_id = frame.first()?.as_u64()?;
+    let unique_id = frame.get(1)?.as_str()?.to_owned();
+
+    match message_type_id {
+        2 if frame.len() == 4 => Some(OcppFrame::Call {
+            unique_id,
+            action: frame[2].as_str()?.to_owned(),
+            payload: frame.pop()?,
+        }),
+        3 if frame.len() == 3 => Some(OcppFrame::CallResult {
+            unique_id,
+            payload: frame.pop()?,
+        }),
+        4 if frame.len() >= 4 => Some(OcppFrame::CallError {
+            unique_id,
+            error_code: frame[2].as_str()?.to_owned(),
+            error_description: frame[3].as_str()?.to_owned(),
+        }),
+        _ => None,
+    }
+}
+
+/// Trace line that passed the filters of a [`ParseConfig`] and holds a valid OCPP-J frame.
+pub struct ParsedLine {
+    pub timestamp: DateTime<FixedOffset>,
+    pub station_id: Option<String>,
+    /// Action of a call, `None` for results, errors and bare payloads.
+    pub action: Option<String>,
+    /// The JSON column, the complete frame.
+    pub json_payload: String,
+}
+
+/// Splits `line` with the [`LineFormat`] of `config` and checks its frame, `None` if the line is
+/// skipped, filtered out or has no valid frame.
+pub fn parse_log_line(line: &str, config: &ParseConfig) -> Option<ParsedLine> {
+    let trace_line = split_trace_line(line, &config.line_format)
+        .ok()
+        .filter(|trace_line| config.keeps(trace_line))?;
+    let action = match parse_ocpp_frame(&trace_line.json)? {
+        OcppFrame::Call { action, .. } => Some(action),
+        _ => None,
+    };
+
+    Some(ParsedLine {
+        timestamp: trace_line.timestamp,
+        station_id: trace_line.station_id,
+        action,
+        json_payload: trace_line.json,
+    })
+}
+
+/// Decodes the meter values of a MeterValues or TransactionEvent call, `None` for any other line.
+pub fn extract_meter_values(parsed: &ParsedLine) -> Option<MeterValuesBatch> {
+    let Some(OcppFrame::Call {
+        action, payload, ..
+    }) = parse_ocpp_frame(&parsed.json_payload)
+    else {
+        return None;
+    };
+
+    // Other calls are never meter values, so don't bother deserializing them.
+    if action != "MeterValues" && action != "TransactionEvent" {
+        return None;
+    }
+
+    match decode_call(&action, &payload) {
+        Ok(Some(OcppMessage::MeterValues(meter_values))) => Some(meter_values),
+        _ => None,
+    }
+}
+
+/// Inclusive window of timestamps that are plotted.
+pub struct TimeRange {
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+impl TimeRange {
+    pub fn new(start: Option<DateTime<FixedOffset>>, end: Option<DateTime<FixedOffset>>) -> Self {
+        Self {
+            start: start.unwrap_or(DateTime::<Utc>::MIN_UTC.fixed_offset()),
+            end: end.unwrap_or(DateTime::<Utc>::MAX_UTC.fixed_offset()),
+        }
+    }
+
+    pub fn contains(&self, timestamp: &DateTime<FixedOffset>) -> bool {
+        self.start <= *timestamp && *timestamp <= self.end
+    }
+
+    /// Same as [`TimeRange::contains`] for the millisecond timestamps stored in the SQLite DB.
+    pub fn contains_millis(&self, timestamp: i64) -> bool {
+        DateTime::from_timestamp_millis(timestamp)
+            .is_some_and(|timestamp| self.contains(&timestamp.fixed_offset()))
+    }
+}
+
+/// Measurands that are plotted, from `--include-measurand` and `--exclude-measurand`.
+pub struct MeasurandFilter {
+    /// All if empty.
+    pub include: Vec<Measurand>,
+    pub exclude: Vec<Measurand>,
+}
+
+impl MeasurandFilter {
+    pub fn matches(&self, measurand: &Measurand) -> bool {
+        (self.include.is_empty() || self.include.contains(measurand))
+            && !self.exclude.contains(measurand)
+    }
+}
+
+pub fn schema_violation(
+    action: &str,
+    violations: Vec<String>,
+    stats: &mut ParseStats,
+) -> Option<OcppMessage> {
+    if violations.is_empty() {
+        return None;
+    }
+    stats.schema_violations += 1;
+
+    Some(OcppMessage::SchemaViolation {
+        action: action.to_owned(),
+        violations,
+    })
+}
+
+/// Settings that decide which trace lines are turned into entries and how they are split up.
+pub struct ParseConfig {
+    pub line_format: LineFormat,
+    pub time_range: TimeRange,
+    pub station_id_filter: Option<String>,
+    /// Connectors to keep messages of, all if empty.
+    pub connector_id_filter: Vec<u32>,
+    /// Transactions to keep messages of, all if empty.
+    pub transaction_id_filter: Vec<String>,
+    pub measurand_filter: MeasurandFilter,
+    /// Actions to decode calls of, all if empty.
+    pub action_filter: Vec<String>,
+    /// Add the JSON of every valid frame as [`OcppMessage::Raw`].
+    pub log_all_messages: bool,
+    /// Calls without a CallResult or CallError after this time are reported as timed out.
+    pub ack_timeout: chrono::TimeDelta,
+    /// Add an [`OcppMessage::SchemaViolation`] for every payload that doesn't match its schema.
+    pub validator: Option<OcppValidator>,
+    /// Process the lines by timestamp instead of in the order they were read.
+    pub sort: bool,
+    /// Convert sampled values in kilo units to their base unit.
+    pub normalize_units: bool,
+    /// Stop after this many lines that were parsed into messages.
+    pub max_lines: Option<usize>,
+}
+
+impl ParseConfig {
+    /// Whether the line is in the time range and of the station to keep.
+    pub fn keeps(&self, trace_line: &TraceLine) -> bool {
+        self.time_range.contains(&trace_line.timestamp)
+            && (self.station_id_filter.is_none() || trace_line.station_id == self.station_id_filter)
+    }
+}
+
+impl Default for ParseConfig {
+    /// Keeps every line of the default [`LineFormat`], without validation or unit conversion.
+    fn default() -> Self {
+        Self {
+            line_format: LineFormat::default(),
+            time_range: TimeRange::new(None, None),
+            station_id_filter: None,
+            connector_id_filter: Vec::new(),
+            transaction_id_filter: Vec::new(),
+            measurand_filter: MeasurandFilter {
+                include: Vec::new(),
+                exclude: Vec::new(),
+            },
+            action_filter: Vec::new(),
+            log_all_messages: false,
+            ack_timeout: chrono::TimeDelta::seconds(60),
+            validator: None,
+            sort: false,
+            normalize_units: false,
+            max_lines: None,
+        }
+    }
+}
+
+pub fn call_latency(
+    action: &str,
+    sent: DateTime<FixedOffset>,
+    received: DateTime<FixedOffset>,
+) -> OcppMessage {
+    OcppMessage::CallLatency {
+        action: action.to_owned(),
+        latency_ms: (received - sent).as_seconds_f64() * 1000.0,
+    }
+}
+
+/// Counts of what happened to the trace lines, for the summary.
+#[derive(Default)]
+pub struct ParseStats {
+    /// Trace files read or downloaded, counting stdin as one.
+    pub files: usize,
+    /// Trace and log files that couldn't be read or are larger than the limit.
+    pub failed_files: usize,
+    pub lines: usize,
+    pub too_few_columns: usize,
+    pub bad_timestamps: usize,
+    pub filtered: usize,
+    /// Lines whose JSON column isn't valid JSON or not a valid OCPP-J frame.
+    pub invalid_frames: usize,
+    pub unknown_actions: usize,
+    /// Calls of a known action whose payload doesn't deserialize.
+    pub invalid_payloads: usize,
+    /// Payloads that don't match their schema, with `--validate-ocpp`.
+    pub schema_violations: usize,
+    pub meter_values: usize,
+    pub other_messages: usize,
+    pub station_ids: HashSet<String>,
+    pub first_timestamp: Option<DateTime<FixedOffset>>,
+    pub last_timestamp: Option<DateTime<FixedOffset>>,
+}
+
+impl ParseStats {
+    /// Lines that had the layout of a trace line but failed to parse.
+    pub fn errors(&self) -> usize {
+        self.invalid_frames + self.invalid_payloads
+    }
+
+    /// Prints the summary to stderr, with the timestamps in `timezone`.
+    pub fn print(&self, timezone: Tz) {
+        let timestamp = |timestamp: Option<DateTime<FixedOffset>>| {
+            timestamp.map_or_else(
+                || "-".to_owned(),
+                |timestamp| timestamp.with_timezone(&timezone).to_rfc3339(),
+            )
+        };
+
+        eprintln!("Files read:                   {}", self.files);
+        eprintln!("Files failed to read:         {}", self.failed_files);
+        eprintln!("Lines read:                   {}", self.lines);
+        eprintln!("Skipped, too few columns:     {}", self.too_few_columns);
+        eprintln!("Skipped, bad timestamp:       {}", self.bad_timestamps);
+        eprintln!("Skipped, filtered out:        {}", self.filtered);
+        eprintln!("Invalid OCPP-J frames:        {}", self.invalid_frames);
+        eprintln!("Unknown OCPP actions:         {}", self.unknown_actions);
+        eprintln!("Invalid payloads:             {}", self.invalid_payloads);
+        eprintln!("Schema violations:            {}", self.schema_violations);
+        eprintln!("Parsed as MeterValues:        {}", self.meter_values);
+        eprintln!("Parsed as other messages:     {}", self.other_messages);
+        eprintln!("Distinct station IDs:         {}", self.station_ids.len());
+        eprintln!(
+            "Earliest timestamp:           {}",
+            timestamp(self.first_timestamp)
+        );
+        eprintln!(
+            "Latest timestamp:             {}",
+            timestamp(self.last_timestamp)
+        );
+    }
+}
+
+/// Call that hasn't been answered yet, with its payload to decode results that depend on it.
+struct PendingCall {
+    action: String,
+    sent: DateTime<FixedOffset>,
+    payload: serde_json::Value,
+}
+
+pub fn parse_trace_file_entries(
+    contents: &[String],
+    config: &ParseConfig,
+) -> anyhow::Result<(Vec<TraceFileEntry>, ParseStats)> {
+    parse_trace_lines(contents, Vec::new(), config)
+}
+
+/// Like [`parse_trace_file_entries`], with `split_lines` that don't need splitting, like the
+/// frames of packet captures. They are parsed after the lines of `contents`, or among them by
+/// timestamp when sorting.
+pub fn parse_trace_lines(
+    contents: &[String],
+    split_lines: Vec<TraceLine>,
+    config: &ParseConfig,
+) -> anyhow::Result<(Vec<TraceFileEntry>, ParseStats)> {
+    // Blank lines, e.g. after the final newline, aren't trace lines.
+    let contents = contents
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    let mut parser = TraceParser::new(config);
+    parser.stats.lines = contents.len() + split_lines.len();
+    let progress = progress_bar(
+        ProgressBar::new_spinner(),
+        "{spinner} {human_pos} lines parsed ({per_sec})",
+    );
+    progress.enable_steady_tick(Duration::from_millis(100));
+    let mut trace_lines = contents
+        .iter()
+        .progress_with(progress.clone())
+        .filter_map(|line| parser.split_line(line))
+        .collect::<Vec<_>>();
+    progress.finish();
+    trace_lines.extend(
+        split_lines
+            .into_iter()
+            .filter(|trace_line| parser.keeps_split_line(trace_line)),
+    );
+    if config.sort {
+        // Stable, so lines with the same timestamp keep their order, e.g. a call and its result.
+        trace_lines.sort_by_key(|trace_line| trace_line.timestamp);
+    }
+
+    let mut trace_file_entries: Vec<TraceFileEntry> = Vec::new();
+    for trace_line in trace_lines {
+        if parser.is_done() {
+            break;
+        }
+        trace_file_entries.extend(parser.parse_trace_line(trace_line));
+    }
+
+    Ok((trace_file_entries, parser.stats))
+}
+
+/// Parses trace lines one at a time, pairing the calls with their results across lines. Used by
+/// [`parse_trace_file_entries`], and directly to parse lines as they are read, in the order they
+/// are read.
+pub struct TraceParser<'a> {
+    config: &'a ParseConfig,
+    pub stats: ParseStats,
+    /// Calls that are still waiting for their CallResult, keyed by station and uniqueId.
+    pending_calls: HashMap<(Option<String>, String), PendingCall>,
+    /// Lines parsed into messages so far, for [`ParseConfig::max_lines`].
+    parsed_lines: usize,
+}
+
+impl<'a> TraceParser<'a> {
+    pub fn new(config: &'a ParseConfig) -> Self {
+        Self {
+            config,
+            stats: ParseStats::default(),
+            pending_calls: HashMap::new(),
+            parsed_lines: 0,
+        }
+    }
+
+    /// Whether [`ParseConfig::max_lines`] lines were parsed, after which all lines are ignored.
+    pub fn is_done(&self) -> bool {
+        self.config
+            .max_lines
+            .is_some_and(|max_lines| self.parsed_lines >= max_lines)
+    }
+
+    /// Parses the next line, which is skipped if it is blank.
+    pub fn parse_line(&mut self, line: &str) -> Vec<TraceFileEntry> {
+        if self.is_done() || line.trim().is_empty() {
+            return Vec::new();
+        }
+
+        self.stats.lines += 1;
+        match self.split_line(line) {
+            Some(trace_line) => self.parse_trace_line(trace_line),
+            None => Vec::new(),
+        }
+    }
+
+    /// Splits `line`, counting it if it is skipped or filtered out.
+    fn split_line(&mut self, line: &str) -> Option<TraceLine> {
+        let config = self.config;
+        let stats = &mut self.stats;
+        split_trace_line(line, &config.line_format)
+            .map_err(|reason| match reason {
+                SkipReason::TooFewColumns => stats.too_few_columns += 1,
+                SkipReason::BadTimestamp => stats.bad_timestamps += 1,
+            })
+            .ok()
+            .filter(|trace_line| {
+                let matches = config.keeps(trace_line);
+                if !matches {
+                    re_log::debug!("Skipping filtered out trace line: {}", line);
+                    stats.filtered += 1;
+                }
+                matches
+            })
+    }
+
+    /// Parses the next line that doesn't need splitting, like a frame of a packet capture.
+    pub fn parse_split_line(&mut self, trace_line: TraceLine) -> Vec<TraceFileEntry> {
+        if self.is_done() {
+            return Vec::new();
+        }
+
+        self.stats.lines += 1;
+        if !self.keeps_split_line(&trace_line) {
+            return Vec::new();
+        }
+        self.parse_trace_line(trace_line)
+    }
+
+    /// Whether the line is kept, counting it if it is filtered out.
+    fn keeps_split_line(&mut self, trace_line: &TraceLine) -> bool {
+        let keeps = self.config.keeps(trace_line);
+        if !keeps {
+            re_log::debug!("Skipping filtered out frame: {}", trace_line.json);
+            self.stats.filtered += 1;
+        }
+        keeps
+    }
+
+    fn parse_trace_line(
+        &mut self,
+        TraceLine {
+            timestamp,
+            station_id,
+            json,
+        }: TraceLine,
+    ) -> Vec<TraceFileEntry> {
+        let config = self.config;
+        let stats = &mut self.stats;
+        let pending_calls = &mut self.pending_calls;
+        stats.station_ids.extend(station_id.clone());
+        stats.first_timestamp = Some(
+            stats
+                .first_timestamp
+                .map_or(timestamp, |t| t.min(timestamp)),
+        );
+        stats.last_timestamp = Some(stats.last_timestamp.map_or(timestamp, |t| t.max(timestamp)));
+
+        // Calls without an answer within the timeout are not going to get one.
+        let mut trace_file_entries = pending_calls
+            .extract_if(|_, call| timestamp - call.sent > config.ack_timeout)
+            .sorted_by_key(|(_, call)| call.sent)
+            .map(|((station_id, unique_id), call)| TraceFileEntry {
+                timestamp: call.sent + config.ack_timeout,
+                station_id,
+                message: OcppMessage::CallTimeout {
+                    action: call.action,
+                    unique_id,
+                    timeout_s: config.ack_timeout.as_seconds_f64(),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let frame = parse_ocpp_frame(json.as_str());
+        let mut messages = Vec::new();
+        if config.log_all_messages {
+            let raw_action = match &frame {
+                Some(OcppFrame::Call { action, .. }) => Some(action.clone()),
+                Some(
+                    OcppFrame::CallResult { unique_id, .. }
+                    | OcppFrame::CallError { unique_id, .. },
+                ) => pending_calls
+                    .get(&(station_id.clone(), unique_id.clone()))
+                    .map(|call| call.action.clone()),
+                Some(OcppFrame::Payload(_)) => Some("Payload".to_owned()),
+                None => None,
+            };
+            messages.extend(
+                raw_action
+                    .filter(|action| {
+                        config.action_filter.is_empty() || config.action_filter.contains(action)
+                    })
+                    .map(|action| OcppMessage::Raw {
+                        action,
+                        json: json.clone(),
+                    }),
+            );
+        }
+        match frame {
+            Some(OcppFrame::Call { action, .. })
+                if !config.action_filter.is_empty() && !config.action_filter.contains(&action) =>
+            {
+                stats.filtered += 1
+            }
+            Some(OcppFrame::Call {
+                unique_id,
+                action,
+                payload,
+            }) => {
+                if let Some(validator) = &config.validator {
+                    messages.extend(schema_violation(
+                        &action,
+                        validator.validate_call(&action, &payload),
+                        stats,
+                    ));
+                }
+                match decode_call(action.as_str(), &payload) {
+                    Ok(message) => messages.extend(message),
+                    Err(DecodeError::UnknownAction) => {
+                        re_log::debug!("Skipping call of unknown action {}", action);
+                        stats.unknown_actions += 1;
+                    }
+                    Err(DecodeError::Payload(error)) => {
+                        re_log::debug!("Skipping {} call with invalid payload: {}", action, error);
+                        stats.invalid_payloads += 1;
+                    }
+                }
+                pending_calls.insert(
+                    (station_id.clone(), unique_id),
+                    PendingCall {
+                        action,
+                        sent: timestamp,
+                        payload,
+                    },
+                );
+            }
+            Some(OcppFrame::CallResult { unique_id, payload }) => {
+                if let Some(PendingCall {
+                    action,
+                    sent,
+                    payload: request,
+                }) = pending_calls.remove(&(station_id.clone(), unique_id))
+                {
+                    messages.push(call_latency(&action, sent, timestamp));
+                    messages.push(OcppMessage::CallAck {
+                        action: action.clone(),
+                    });
+                    if let Some(validator) = &config.validator {
+                        messages.extend(schema_violation(
+                            &format!("{}Response", action),
+                            validator.validate_call_result(&action, &payload),
+                            stats,
+                        ));
+                    }
+                    messages.extend(decode_call_result(action.as_str(), &request, payload));
+                }
+            }
+            Some(OcppFrame::CallError {
+                unique_id,
+                error_code,
+                error_description,
+            }) => {
+                let action = match pending_calls.remove(&(station_id.clone(), unique_id)) {
+                    Some(call) => {
+                        messages.push(call_latency(&call.action, call.sent, timestamp));
+                        call.action
+                    }
+                    None => "Unknown".to_owned(),
+                };
+                if config.action_filter.is_empty() || config.action_filter.contains(&action) {
+                    messages.push(OcppMessage::CallError {
+                        action,
+                        error_code,
+                        error_description,
+                    });
+                }
+            }
+            Some(OcppFrame::Payload(payload)) => {
+                messages.extend(decode_payload(payload, &config.action_filter))
+            }
+            None => {
+                re_log::debug!("Skipping trace line with invalid OCPP-J frame: {}", json);
+                stats.invalid_frames += 1;
+            }
+        };
+
+        for message in &mut messages {
+            if let OcppMessage::MeterValues(meter_values) = message {
+                meter_values
+                    .readings
+                    .retain(|reading| config.measurand_filter.matches(&reading.measurand));
+                if config.normalize_units {
+                    meter_values
+                        .readings
+                        .iter_mut()
+                        .for_each(SampledReading::normalize_unit);
+                }
+            }
+        }
+
+        messages.retain(|message| {
+            let matches = message.matches_connectors(&config.connector_id_filter)
+                && message.matches_transactions(&config.transaction_id_filter);
+            if !matches {
+                stats.filtered += 1;
+            }
+            matches
+        });
+
+        let data_messages = stats.meter_values + stats.other_messages;
+        for message in &messages {
+            match message {
+                OcppMessage::MeterValues(meter_values) => {
+                    re_log::debug!(
+                        "Parsed MeterValues at {} with {}",
+                        timestamp,
+                        meter_values
+                            .readings
+                            .iter()
+                            .map(|reading| ocpp_name(&reading.measurand))
+                            .unique()
+                            .join(", ")
+                    );
+                    stats.meter_values += 1;
+                }
+                OcppMessage::CallLatency { .. }
+                | OcppMessage::CallAck { .. }
+                | OcppMessage::SchemaViolation { .. }
+                | OcppMessage::Raw { .. } => {}
+                _ => stats.other_messages += 1,
+            }
+        }
+
+        if stats.meter_values + stats.other_messages > data_messages {
+            self.parsed_lines += 1;
+            if self.is_done() {
+                re_log::warn!(
+                    "Stopped after --max-lines {} parsed lines, the output is incomplete",
+                    self.parsed_lines
+                );
+            }
+        }
+
+        trace_file_entries.extend(messages.into_iter().map(|message| TraceFileEntry {
+            timestamp,
+            station_id: station_id.clone(),
+            message,
+        }));
+
+        trace_file_entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Timelike, Utc};
+    use proptest::prelude::*;
+    use rust_ocpp::v1_6::types::ConfigurationStatus;
+
+    fn utc(date_time: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(date_time).unwrap()
+    }
+
+    #[test]
+    fn normal_timestamp() {
+        assert_eq!(
+            parse_log_timestamp("2024-01-15", "08:23:10"),
+            Some(utc("2024-01-15T08:23:10Z"))
+        );
+    }
+
+    #[test]
+    fn fractional_seconds() {
+        let timestamp = parse_log_timestamp("2024-01-15", "08:23:10.123456").unwrap();
+
+        assert_eq!(timestamp.nanosecond(), 123_456_000);
+        assert_eq!(timestamp, utc("2024-01-15T08:23:10.123456Z"));
+    }
+
+    #[test]
+    fn missing_timezone_is_utc() {
+        let timestamp = parse_log_timestamp("2024-01-15", "08:23:10").unwrap();
+
+        assert_eq!(timestamp.offset().local_minus_utc(), 0);
+    }
+
+    #[test]
+    fn implicit_utc_is_the_same_instant_as_utc() {
+        assert_eq!(
+            parse_log_timestamp("2024-06-01", "12:00:00"),
+            Some(
+                Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0)
+                    .unwrap()
+                    .fixed_offset()
+            )
+        );
+    }
+
+    #[test]
+    fn garbage_input() {
+        assert_eq!(parse_log_timestamp("yesterday", "noon"), None);
+        assert_eq!(parse_log_timestamp("", ""), None);
+        assert_eq!(parse_log_timestamp("2024-13-45", "08:23:10"), None);
+        assert_eq!(parse_log_timestamp("2024-01-15", "25:61:00"), None);
+    }
+
+    #[test]
+    fn midnight_boundary() {
+        let before = parse_log_timestamp("2024-01-15", "23:59:59.999").unwrap();
+        let midnight = parse_log_timestamp("2024-01-16", "00:00:00").unwrap();
+
+        assert_eq!((midnight - before).num_milliseconds(), 1);
+        assert_eq!(parse_log_timestamp("2024-01-15", "24:00:00"), None);
+    }
+
+    #[test]
+    fn y2k_adjacent_dates() {
+        let before = parse_log_timestamp("1999-12-31", "23:59:59").unwrap();
+        let after = parse_log_timestamp("2000-01-01", "00:00:00").unwrap();
+
+        assert_eq!((after - before).num_seconds(), 1);
+        assert_eq!(
+            parse_log_timestamp("2000-02-29", "12:00:00"),
+            Some(utc("2000-02-29T12:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn dst_transitions_do_not_apply() {
+        // 02:30 doesn't exist in Central European time on this day and 02:30 exists twice on the
+        // later one, in UTC both are ordinary timestamps.
+        assert_eq!(
+            parse_log_timestamp("2024-03-31", "02:30:00"),
+            Some(utc("2024-03-31T02:30:00Z"))
+        );
+        let first = parse_log_timestamp("2024-10-27", "02:30:00").unwrap();
+        let second = parse_log_timestamp("2024-10-27", "03:30:00").unwrap();
+        assert_eq!((second - first).num_hours(), 1);
+    }
+
+    #[test]
+    fn future_dates() {
+        assert_eq!(
+            parse_log_timestamp("2099-12-31", "23:59:59"),
+            Some(utc("2099-12-31T23:59:59Z"))
+        );
+    }
+
+    #[test]
+    fn dates_before_1970() {
+        let timestamp = parse_log_timestamp("1969-07-20", "20:17:40").unwrap();
+
+        assert!(timestamp.timestamp() < 0);
+        assert_eq!(timestamp, utc("1969-07-20T20:17:40Z"));
+    }
+
+    #[test]
+    fn bracketed_date() {
+        assert_eq!(
+            parse_log_timestamp("[2024-01-15", "08:23:10]"),
+            None,
+            "only the opening bracket is stripped"
+        );
+        assert_eq!(
+            parse_log_timestamp("[2024-01-15", "08:23:10"),
+            Some(utc("2024-01-15T08:23:10Z"))
+        );
+    }
+
+    #[test]
+    fn rfc3339_in_the_date_column() {
+        assert_eq!(
+            parse_log_timestamp("2024-01-15T08:23:10+02:00", "INFO"),
+            Some(utc("2024-01-15T06:23:10Z"))
+        );
+    }
+
+    #[test]
+    fn custom_format() {
+        assert_eq!(
+            parse_trace_timestamp("15.01.2024", "08:23:10", Some("%d.%m.%Y %H:%M:%S"), Tz::UTC),
+            Some(utc("2024-01-15T08:23:10Z"))
+        );
+        assert_eq!(
+            parse_trace_timestamp(
+                "15.01.2024",
+                "08:23:10+0100",
+                Some("%d.%m.%Y %H:%M:%S%z"),
+                Tz::UTC
+            ),
+            Some(utc("2024-01-15T07:23:10Z"))
+        );
+    }
+
+    #[test]
+    fn tab_delimiter() {
+        let format = LineFormat {
+            json_column: 3,
+            min_columns: 4,
+            delimiter: Some('\t'),
+            ..Default::default()
+        };
+        let Ok(trace_line) = split_trace_line(
+            "2024-01-15\t08:23:10\tEVSE-001\t[2, \"h1\", \"Heartbeat\", {}]",
+            &format,
+        ) else {
+            panic!("Tab-separated line was skipped");
+        };
+
+        assert_eq!(trace_line.timestamp, utc("2024-01-15T08:23:10Z"));
+        assert_eq!(trace_line.json, r#"[2, "h1", "Heartbeat", {}]"#);
+    }
+
+    #[test]
+    fn json_with_whitespace() {
+        let line = r#"2024-01-15 08:23:10 INFO ws EVSE-001 -> CP Call Heartbeat [2, "h1", "Heartbeat", {}]"#;
+        let Ok(trace_line) = split_trace_line(line, &LineFormat::default()) else {
+            panic!("Line was skipped");
+        };
+        assert_eq!(trace_line.json, r#"[2, "h1", "Heartbeat", {}]"#);
+
+        let format = LineFormat {
+            json_col_last: false,
+            ..Default::default()
+        };
+        let Ok(trace_line) = split_trace_line(line, &format) else {
+            panic!("Line was skipped");
+        };
+        assert_eq!(trace_line.json, "[2,");
+    }
+
+    #[test]
+    fn bom_at_start_of_file() {
+        let content = "\u{feff}2024-01-15 08:23:10 INFO ws EVSE-001 -> CP Call Heartbeat [2,\"h1\",\"Heartbeat\",{}]";
+        let config = ParseConfig::default();
+
+        assert!(parse_log_line(content, &config).is_none());
+        let Some(parsed) = parse_log_line(strip_bom(content), &config) else {
+            panic!("Line was skipped after stripping the BOM");
+        };
+        assert_eq!(parsed.timestamp, utc("2024-01-15T08:23:10Z"));
+        assert_eq!(parsed.action.as_deref(), Some("Heartbeat"));
+        assert_eq!(strip_bom("no BOM"), "no BOM");
+    }
+
+    #[test]
+    fn crlf_line_ending() {
+        let line =
+            "2024-01-15 08:23:10 INFO ws EVSE-001 -> CP Call Heartbeat [2,\"h1\",\"Heartbeat\",{}]";
+        let Ok(trace_line) = split_trace_line(&format!("{}\r", line), &LineFormat::default())
+        else {
+            panic!("CRLF line was skipped");
+        };
+
+        assert_eq!(trace_line.timestamp, utc("2024-01-15T08:23:10Z"));
+        assert_eq!(trace_line.json, r#"[2,"h1","Heartbeat",{}]"#);
+
+        let lines = [line, line]
+            .map(|line| format!("{}\r\n", line))
+            .concat()
+            .split("\n")
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        let (_, stats) = parse_trace_file_entries(&lines, &ParseConfig::default()).unwrap();
+        assert_eq!(stats.lines, 2);
+        assert_eq!(stats.other_messages, 2);
+        assert_eq!(stats.errors(), 0);
+    }
+
+    #[test]
+    fn local_time_of_timezone() {
+        let timestamp =
+            parse_trace_timestamp("2024-01-15", "09:23:10", None, Tz::Europe__Berlin).unwrap();
+
+        assert_eq!(timestamp, utc("2024-01-15T08:23:10Z"));
+        assert_eq!(timestamp.offset().local_minus_utc(), 0);
+        assert_eq!(
+            parse_trace_timestamp("2024-07-15", "09:23:10", None, Tz::Europe__Berlin),
+            Some(utc("2024-07-15T07:23:10Z"))
+        );
+    }
+
+    #[test]
+    fn timezone_does_not_apply_to_timestamps_with_offset() {
+        assert_eq!(
+            parse_trace_timestamp(
+                "2024-01-15T08:23:10+02:00",
+                "INFO",
+                None,
+                Tz::Europe__Berlin
+            ),
+            Some(utc("2024-01-15T06:23:10Z"))
+        );
+    }
+
+    #[test]
+    fn dst_transitions_of_timezone() {
+        assert_eq!(
+            parse_trace_timestamp("2024-03-31", "02:30:00", None, Tz::Europe__Berlin),
+            None,
+            "skipped when the clocks were put forward"
+        );
+        assert_eq!(
+            parse_trace_timestamp("2024-10-27", "02:30:00", None, Tz::Europe__Berlin),
+            Some(utc("2024-10-27T00:30:00Z")),
+            "the first of the repeated hour"
+        );
+    }
+
+    #[test]
+    fn parse_log_line_with_frame() {
+        let config = ParseConfig::default();
+        let parsed = parse_log_line(
+            r#"2024-01-15 08:23:10 INFO ws EVSE-001 -> CP Call Heartbeat [2,"h1","Heartbeat",{}]"#,
+            &config,
+        )
+        .unwrap();
+
+        assert_eq!(parsed.timestamp, utc("2024-01-15T08:23:10Z"));
+        assert_eq!(parsed.action.as_deref(), Some("Heartbeat"));
+        assert!(extract_meter_values(&parsed).is_none());
+        assert!(
+            parse_log_line(
+                "2024-01-15 08:23:10 INFO ws EVSE-001 -> CP Call Heartbeat {oops",
+                &config
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn parse_log_line_applies_filters() {
+        let config = ParseConfig {
+            line_format: LineFormat {
+                station_id_column: Some(4),
+                ..Default::default()
+            },
+            station_id_filter: Some("EVSE-002".to_owned()),
+            ..Default::default()
+        };
+
+        assert!(
+            parse_log_line(
+                r#"2024-01-15 08:23:10 INFO ws EVSE-001 -> CP Call Heartbeat [2,"h1","Heartbeat",{}]"#,
+                &config,
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn extract_meter_values_of_call() {
+        let parsed = parse_log_line(
+            r#"2024-01-15 08:23:10 INFO ws EVSE-001 -> CP Call MeterValues [2,"m1","MeterValues",{"connectorId":1,"meterValue":[{"timestamp":"2024-01-15T08:23:10Z","sampledValue":[{"value":"230.1","measurand":"Voltage"}]}]}]"#,
+            &ParseConfig::default(),
+        )
+        .unwrap();
+        let meter_values = extract_meter_values(&parsed).unwrap();
+
+        assert_eq!(parsed.action.as_deref(), Some("MeterValues"));
+        assert_eq!(meter_values.connector_id, Some(1));
+        assert_eq!(meter_values.readings.len(), 1);
+    }
+
+    #[test]
+    fn change_configuration_is_paired_with_its_result() {
+        let lines = [
+            r#"2024-01-15 08:23:10 INFO ws EVSE-001 <- CS Call ChangeConfiguration [2,"c1","ChangeConfiguration",{"key":"HeartbeatInterval","value":"300"}]"#,
+            r#"2024-01-15 08:23:11 INFO ws EVSE-001 -> CP CallResult ChangeConfiguration [3,"c1",{"status":"Rejected"}]"#,
+        ]
+        .map(str::to_owned);
+        let (entries, stats) = parse_trace_file_entries(&lines, &ParseConfig::default()).unwrap();
+
+        assert_eq!(stats.other_messages, 1);
+        assert!(entries.iter().any(|entry| matches!(
+            &entry.message,
+            OcppMessage::ConfigurationChange { key, value, status: ConfigurationStatus::Rejected }
+                if key == "HeartbeatInterval" && value == "300"
+        )));
+    }
+
+    #[test]
+    fn trace_parser_pairs_results_of_earlier_lines() {
+        let config = ParseConfig::default();
+        let mut parser = TraceParser::new(&config);
+
+        assert!(parser.parse_line(r#"2024-01-15 08:23:10 INFO ws EVSE-001 <- CS Call ChangeConfiguration [2,"c1","ChangeConfiguration",{"key":"HeartbeatInterval","value":"300"}]"#).is_empty());
+        assert!(parser.parse_line("").is_empty());
+        let entries = parser.parse_line(r#"2024-01-15 08:23:11 INFO ws EVSE-001 -> CP CallResult ChangeConfiguration [3,"c1",{"status":"Accepted"}]"#);
+
+        assert!(entries.iter().any(|entry| matches!(
+            &entry.message,
+            OcppMessage::ConfigurationChange {
+                status: ConfigurationStatus::Accepted,
+                ..
+            }
+        )));
+        assert_eq!(parser.stats.lines, 2);
+        assert_eq!(parser.stats.other_messages, 1);
+        assert_eq!(
+            parser.stats.first_timestamp,
+            Some(utc("2024-01-15T08:23:10Z"))
+        );
+        assert_eq!(
+            parser.stats.last_timestamp,
+            Some(utc("2024-01-15T08:23:11Z"))
+        );
+    }
+
+    #[test]
+    fn soap_request_is_a_call() {
+        let Some(OcppFrame::Call {
+            unique_id,
+            action,
+            payload,
+        }) = parse_ocpp_frame(
+            r#"<?xml version="1.0"?> <s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://www.w3.org/2005/08/addressing"><s:Header><a:MessageID>b1</a:MessageID></s:Header><s:Body><bootNotificationRequest xmlns="urn://Ocpp/Cs/2015/10/"><chargePointVendor>ACME &amp; Co</chargePointVendor><chargePointModel>Wallbox11</chargePointModel></bootNotificationRequest></s:Body></s:Envelope>"#,
+        )
+        else {
+            panic!("Not a call");
+        };
+
+        assert_eq!(unique_id, "b1");
+        assert_eq!(action, "BootNotification");
+        assert_eq!(payload["chargePointVendor"], "ACME & Co");
+    }
+
+    #[test]
+    fn extract_meter_values_of_soap_call() {
+        let parsed = parse_log_line(
+            r#"2024-01-15 08:23:10 INFO soap EVSE-001 -> CS Request MeterValues <s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body><meterValuesRequest xmlns="urn://Ocpp/Cs/2015/10/"><connectorId>1</connectorId><transactionId>7</transactionId><meterValue><timestamp>2024-01-15T08:23:10Z</timestamp><sampledValue><value>230.1</value><measurand>Voltage</measurand><phase>L1</phase></sampledValue></meterValue></meterValuesRequest></s:Body></s:Envelope>"#,
+            &ParseConfig::default(),
+        )
+        .unwrap();
+        let meter_values = extract_meter_values(&parsed).unwrap();
+
+        assert_eq!(parsed.action.as_deref(), Some("MeterValues"));
+        assert_eq!(meter_values.connector_id, Some(1));
+        assert_eq!(meter_values.transaction_id.as_deref(), Some("7"));
+        assert_eq!(meter_values.readings.len(), 1);
+        assert_eq!(meter_values.readings[0].value, 230.1);
+    }
+
+    #[test]
+    fn ocpp_15_soap_meter_values() {
+        let parsed = parse_log_line(
+            r#"2024-01-15 08:23:10 INFO soap EVSE-001 -> CS Request MeterValues <soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope"><soap:Body><meterValuesRequest xmlns="urn://Ocpp/Cs/2012/06/"><connectorId>1</connectorId><values><timestamp>2024-01-15T08:23:10Z</timestamp><value measurand="Energy.Active.Import.Register" unit="Wh">1310</value><value measurand="Power.Active.Import" unit="W">3600</value></values></meterValuesRequest></soap:Body></soap:Envelope>"#,
+            &ParseConfig::default(),
+        )
+        .unwrap();
+        let meter_values = extract_meter_values(&parsed).unwrap();
+
+        assert_eq!(meter_values.readings.len(), 2);
+        assert_eq!(
+            meter_values.readings[0].measurand,
+            Measurand::EnergyActiveImportRegister
+        );
+        assert_eq!(meter_values.readings[0].value, 1310.0);
+    }
+
+    #[test]
+    fn soap_fault_is_paired_with_its_request() {
+        let lines = [
+            r#"2024-01-15 08:23:10 INFO soap EVSE-001 -> CS Request Heartbeat <s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://www.w3.org/2005/08/addressing"><s:Header><a:MessageID>h1</a:MessageID></s:Header><s:Body><heartbeatRequest xmlns="urn://Ocpp/Cs/2015/10/"/></s:Body></s:Envelope>"#,
+            r#"2024-01-15 08:23:11 INFO soap EVSE-001 <- CS Fault Heartbeat <s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://www.w3.org/2005/08/addressing"><s:Header><a:RelatesTo>h1</a:RelatesTo></s:Header><s:Body><s:Fault><s:Code><s:Value>s:Receiver</s:Value><s:Subcode><s:Value>InternalError</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en">Database unavailable</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#,
+        ]
+        .map(str::to_owned);
+        let (entries, stats) = parse_trace_file_entries(&lines, &ParseConfig::default()).unwrap();
+
+        assert_eq!(stats.invalid_frames, 0);
+        assert!(entries.iter().any(|entry| matches!(
+            &entry.message,
+            OcppMessage::CallError { action, error_code, error_description }
+                if action == "Heartbeat"
+                    && error_code == "InternalError"
+                    && error_description == "Database unavailable"
+        )));
+    }
+
+    /// A column of a trace line, without the whitespace that separates them.
+    fn column() -> impl Strategy<Value = String> {
+        "[^\\s]{1,16}"
+    }
+
+    fn date_and_time() -> impl Strategy<Value = (String, String)> {
+        (
+            1970..2100i32,
+            1..=12u32,
+            1..=28u32,
+            0..24u32,
+            0..60u32,
+            0..60u32,
+        )
+            .prop_map(|(year, month, day, hour, minute, second)| {
+                (
+                    format!("{:04}-{:02}-{:02}", year, month, day),
+                    format!("{:02}:{:02}:{:02}", hour, minute, second),
+                )
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn split_trace_line_never_panics(line in any::<String>()) {
+            let _ = split_trace_line(&line, &LineFormat::default());
+        }
+
+        #[test]
+        fn parse_ocpp_frame_never_panics(json in any::<String>()) {
+            let _ = parse_ocpp_frame(&json);
+        }
+
+        #[test]
+        fn lines_with_ten_columns_reach_the_json(
+            (date, time) in date_and_time(),
+            columns in proptest::collection::vec(column(), 8),
+        ) {
+            let line = format!("{} {} {}", date, time, columns.join(" "));
+
+            let trace_line = split_trace_line(&line, &LineFormat::default())
+                .map_err(|_| TestCaseError::fail(format!("Skipped `{}`", line)))?;
+            prop_assert_eq!(&trace_line.json, &columns[7]);
+            // The JSON column is attempted no matter what it holds and fails gracefully.
+            let _ = parse_ocpp_frame(&trace_line.json);
+        }
+
+        #[test]
+        fn lines_with_nine_columns_reach_the_json(
+            (date, time) in date_and_time(),
+            columns in proptest::collection::vec(column(), 7),
+        ) {
+            let line = format!("{} {} {}", date, time, columns.join(" "));
+
+            let trace_line = split_trace_line(&line, &LineFormat::default())
+                .map_err(|_| TestCaseError::fail(format!("Skipped `{}`", line)))?;
+            prop_assert_eq!(&trace_line.json, &columns[6]);
+        }
+
+        #[test]
+        fn lines_with_fewer_columns_are_skipped(
+            columns in proptest::collection::vec(column(), 0..9),
+        ) {
+            prop_assert!(matches!(
+                split_trace_line(&columns.join(" "), &LineFormat::default()),
+                Err(SkipReason::TooFewColumns)
+            ));
+        }
+    }
+}
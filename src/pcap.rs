@@ -0,0 +1,741 @@
+//! Reading of the OCPP-J frames of WebSocket connections in PCAP and PCAPNG packet captures. The
+//! TCP streams are reassembled from their segments and every text message becomes a trace line
+//! with the capture time of the packet that completed it.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use chrono::DateTime;
+use pcap_file::DataLink;
+use pcap_file::pcap::PcapReader;
+use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionOption;
+use pcap_file::pcapng::{Block, PcapNgReader};
+use pktparse::ethernet::{EtherType, parse_vlan_ethernet_frame};
+use pktparse::ip::IPProtocol;
+use pktparse::ipv4::parse_ipv4_header;
+use pktparse::ipv6::parse_ipv6_header;
+use pktparse::tcp::{TcpHeader, parse_tcp_header};
+use rerun::external::re_log;
+
+use crate::parsing::TraceLine;
+
+/// TCP ports the WebSocket connections are looked for on.
+pub const DEFAULT_PORTS: [u16; 3] = [80, 443, 8080];
+
+/// Larger handshakes and messages are taken for a stream that isn't a WebSocket connection.
+const MAX_BUFFERED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Segments that arrived ahead of a gap, after which the missing ones are taken as not captured.
+const MAX_OUT_OF_ORDER_SEGMENTS: usize = 256;
+
+/// The packet capture magic of PCAPNG files, which start with their section header block.
+const PCAPNG_MAGIC: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+
+/// IP address and TCP port.
+type Endpoint = (IpAddr, u16);
+
+enum Stage {
+    /// Before the HTTP upgrade request or response.
+    Handshake,
+    Frames,
+    /// What follows can't be decoded, e.g. after segments that weren't captured.
+    Unreadable,
+}
+
+/// One direction of a WebSocket connection.
+struct TcpStream {
+    /// Sequence number of the next byte, from the SYN or the first captured segment.
+    next_seq: Option<u32>,
+    /// Payloads of the segments after a gap, by sequence number.
+    out_of_order: HashMap<u32, Vec<u8>>,
+    /// Reassembled bytes that aren't decoded yet.
+    buffer: Vec<u8>,
+    stage: Stage,
+    /// HTTP upgrade request or response.
+    handshake: Option<String>,
+    /// Opcode and payload so far of a message that is split into several frames.
+    message: Option<(u8, Vec<u8>)>,
+}
+
+impl TcpStream {
+    fn new() -> Self {
+        Self {
+            next_seq: None,
+            out_of_order: HashMap::new(),
+            buffer: Vec::new(),
+            stage: Stage::Handshake,
+            handshake: None,
+            message: None,
+        }
+    }
+
+    /// Adds the payload of a segment, dropping the bytes of retransmissions.
+    fn add_segment(&mut self, seq: u32, payload: &[u8]) {
+        let next_seq = *self.next_seq.get_or_insert(seq);
+        if (seq.wrapping_sub(next_seq) as i32) > 0 {
+            self.out_of_order.insert(seq, payload.to_vec());
+            if self.out_of_order.len() > MAX_OUT_OF_ORDER_SEGMENTS {
+                self.skip("segments are missing from the capture");
+            }
+            return;
+        }
+        self.append(seq, payload);
+
+        while let Some(next_seq) = self.next_seq
+            && let Some(seq) = self
+                .out_of_order
+                .keys()
+                .copied()
+                .find(|seq| seq.wrapping_sub(next_seq) as i32 <= 0)
+        {
+            let payload = self.out_of_order.remove(&seq).unwrap_or_default();
+            self.append(seq, &payload);
+        }
+    }
+
+    /// Appends the bytes of the payload starting at `seq` that come after the ones already seen.
+    fn append(&mut self, seq: u32, payload: &[u8]) {
+        let Some(next_seq) = self.next_seq else {
+            return;
+        };
+        let seen = next_seq.wrapping_sub(seq) as usize;
+        if let Some(new) = payload.get(seen..).filter(|new| !new.is_empty()) {
+            self.buffer.extend_from_slice(new);
+            self.next_seq = Some(next_seq.wrapping_add(new.len() as u32));
+        }
+    }
+
+    fn skip(&mut self, reason: &str) {
+        if !matches!(self.stage, Stage::Unreadable) {
+            re_log::debug!("Skipping the rest of a TCP stream, {}", reason);
+        }
+        self.stage = Stage::Unreadable;
+        self.buffer.clear();
+        self.out_of_order.clear();
+    }
+
+    /// Decodes the handshake and frames in the buffer, and returns the complete text messages.
+    /// Frames from the client are masked, the ones from the server aren't.
+    fn decode(&mut self, masked: bool) -> Vec<String> {
+        let mut messages = Vec::new();
+        loop {
+            match self.stage {
+                Stage::Handshake => {
+                    let is_http = [b"GET ".as_slice(), b"HTTP/"].iter().any(|prefix| {
+                        self.buffer.starts_with(prefix) || prefix.starts_with(&self.buffer)
+                    });
+                    if !is_http {
+                        // The capture started after the handshake.
+                        self.stage = Stage::Frames;
+                        continue;
+                    }
+                    let Some(end) = self
+                        .buffer
+                        .windows(4)
+                        .position(|window| window == b"\r\n\r\n")
+                    else {
+                        if self.buffer.len() > MAX_BUFFERED_BYTES {
+                            self.skip("the HTTP headers don't end");
+                        }
+                        break;
+                    };
+
+                    let handshake =
+                        String::from_utf8_lossy(&self.buffer.drain(..end + 4).collect::<Vec<_>>())
+                            .into_owned();
+                    let status_line = handshake.lines().next().unwrap_or_default();
+                    if status_line.starts_with("HTTP/") && !status_line.contains(" 101 ") {
+                        self.skip("the server didn't switch to WebSocket");
+                        break;
+                    }
+                    self.handshake = Some(handshake);
+                    self.stage = Stage::Frames;
+                }
+                Stage::Frames => {
+                    let (frame, length) = match parse_frame(&self.buffer, masked) {
+                        Ok(frame) => frame,
+                        Err(FrameError::Incomplete) => break,
+                        Err(FrameError::Invalid(reason)) => {
+                            self.skip(reason);
+                            break;
+                        }
+                    };
+                    self.buffer.drain(..length);
+
+                    if !matches!(
+                        frame.opcode,
+                        OPCODE_CONTINUATION | OPCODE_TEXT | OPCODE_BINARY
+                    ) {
+                        // Close, ping and pong.
+                        continue;
+                    }
+                    if frame.compressed {
+                        re_log::warn!(
+                            "Skipping WebSocket messages compressed with permessage-deflate"
+                        );
+                        self.skip("the messages are compressed");
+                        break;
+                    }
+                    let message = match frame.opcode {
+                        OPCODE_CONTINUATION => self.message.as_mut(),
+                        opcode => Some(self.message.insert((opcode, Vec::new()))),
+                    };
+                    // Otherwise the continuation of a message from before the capture.
+                    if let Some((_, payload)) = message {
+                        payload.extend_from_slice(&frame.payload);
+                    }
+                    if frame.fin
+                        && let Some((OPCODE_TEXT, payload)) = self.message.take()
+                    {
+                        messages.push(String::from_utf8_lossy(&payload).into_owned());
+                    }
+                }
+                Stage::Unreadable => {
+                    self.buffer.clear();
+                    break;
+                }
+            }
+        }
+
+        messages
+    }
+}
+
+struct Frame {
+    fin: bool,
+    /// RSV1, set on messages compressed with permessage-deflate.
+    compressed: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+enum FrameError {
+    Incomplete,
+    Invalid(&'static str),
+}
+
+/// Parses the WebSocket frame at the start of `data` and returns it with its length, unmasking
+/// its payload. Frames that break the rules of RFC 6455 mean that `data` doesn't start at a frame.
+fn parse_frame(data: &[u8], masked: bool) -> Result<(Frame, usize), FrameError> {
+    let [first, second, ..] = *data else {
+        return Err(FrameError::Incomplete);
+    };
+    let opcode = first & 0x0f;
+    if first & 0x30 != 0 {
+        return Err(FrameError::Invalid("a frame has reserved bits set"));
+    }
+    if !matches!(opcode, 0x0..=0x2 | 0x8..=0xa) {
+        return Err(FrameError::Invalid("a frame has an unknown opcode"));
+    }
+    if (second & 0x80 != 0) != masked {
+        return Err(FrameError::Invalid("a frame is masked the wrong way"));
+    }
+
+    let (length, mut offset) = match second & 0x7f {
+        126 => (
+            data.get(2..4)
+                .map(|length| u16::from_be_bytes([length[0], length[1]]).into())
+                .ok_or(FrameError::Incomplete)?,
+            4,
+        ),
+        127 => (
+            data.get(2..10)
+                .and_then(|length| <[u8; 8]>::try_from(length).ok())
+                .map(u64::from_be_bytes)
+                .ok_or(FrameError::Incomplete)?,
+            10,
+        ),
+        length => (length.into(), 2),
+    };
+    if length > MAX_BUFFERED_BYTES as u64 {
+        return Err(FrameError::Invalid("a frame is too large"));
+    }
+    let mask = if masked {
+        let mask = data.get(offset..offset + 4).ok_or(FrameError::Incomplete)?;
+        offset += 4;
+        Some(mask)
+    } else {
+        None
+    };
+
+    let end = offset + length as usize;
+    let payload = data.get(offset..end).ok_or(FrameError::Incomplete)?;
+    let payload = match mask {
+        Some(mask) => payload
+            .iter()
+            .zip(mask.iter().cycle())
+            .map(|(byte, mask)| byte ^ mask)
+            .collect(),
+        None => payload.to_vec(),
+    };
+
+    Ok((
+        Frame {
+            fin: first & 0x80 != 0,
+            compressed: first & 0x40 != 0,
+            opcode,
+            payload,
+        },
+        end,
+    ))
+}
+
+struct Connection {
+    to_server: TcpStream,
+    to_client: TcpStream,
+}
+
+impl Connection {
+    /// Last segment of the path of the upgrade request, where OCPP-J puts the charge point ID:
+    /// `EVSE-001` of `GET /ocpp/EVSE-001 HTTP/1.1`.
+    fn station_id(&self) -> Option<String> {
+        let request_line = self.to_server.handshake.as_deref()?.lines().next()?;
+        let path = request_line.strip_prefix("GET ")?.split(' ').next()?;
+
+        path.split(['?', '#'])
+            .next()?
+            .rsplit('/')
+            .find(|segment| !segment.is_empty())
+            .map(str::to_owned)
+    }
+}
+
+/// The WebSocket connections of a capture, keyed by client and server.
+struct Capture<'a> {
+    ports: &'a [u16],
+    connections: HashMap<(Endpoint, Endpoint), Connection>,
+    trace_lines: Vec<TraceLine>,
+}
+
+impl Capture<'_> {
+    /// Adds a captured packet, `timestamp` being the time since the Unix epoch.
+    fn add_packet(&mut self, timestamp: Duration, datalink: DataLink, data: &[u8]) {
+        let Some((source, dest, tcp, payload)) = ip_packet(datalink, data).and_then(tcp_segment)
+        else {
+            return;
+        };
+        let (client, server, to_server) = if self.ports.contains(&dest.1) {
+            (source, dest, true)
+        } else if self.ports.contains(&source.1) {
+            (dest, source, false)
+        } else {
+            return;
+        };
+
+        let new_connection = || Connection {
+            to_server: TcpStream::new(),
+            to_client: TcpStream::new(),
+        };
+        // Replaces an earlier connection between the same ports.
+        if tcp.flag_syn && !tcp.flag_ack {
+            self.connections.insert((client, server), new_connection());
+        }
+        let connection = self
+            .connections
+            .entry((client, server))
+            .or_insert_with(new_connection);
+        let stream = if to_server {
+            &mut connection.to_server
+        } else {
+            &mut connection.to_client
+        };
+        if tcp.flag_syn {
+            stream.next_seq = Some(tcp.sequence_no.wrapping_add(1));
+            return;
+        }
+        if payload.is_empty() {
+            return;
+        }
+
+        stream.add_segment(tcp.sequence_no, payload);
+        let messages = stream.decode(to_server);
+        let Some(timestamp) = i64::try_from(timestamp.as_secs())
+            .ok()
+            .and_then(|secs| DateTime::from_timestamp(secs, timestamp.subsec_nanos()))
+        else {
+            return;
+        };
+        let station_id = connection.station_id();
+        self.trace_lines
+            .extend(messages.into_iter().map(|json| TraceLine {
+                timestamp: timestamp.fixed_offset(),
+                station_id: station_id.clone(),
+                json,
+            }));
+    }
+}
+
+/// The IP packet in a link layer frame, if it has one.
+fn ip_packet(datalink: DataLink, data: &[u8]) -> Option<&[u8]> {
+    match datalink {
+        DataLink::ETHERNET => {
+            let (packet, frame) = parse_vlan_ethernet_frame(data).ok()?;
+            matches!(frame.ethertype, EtherType::IPv4 | EtherType::IPv6).then_some(packet)
+        }
+        // Linux cooked captures, e.g. of `tcpdump -i any`.
+        DataLink::LINUX_SLL => data.get(16..),
+        DataLink::LINUX_SLL2 => data.get(20..),
+        // Loopback, with the address family in front of the packet.
+        DataLink::NULL | DataLink::LOOP => data.get(4..),
+        DataLink::RAW | DataLink::IPV4 | DataLink::IPV6 => Some(data),
+        _ => None,
+    }
+}
+
+/// Source and destination of the TCP segment in an IP packet, with its header and payload.
+/// Fragmented IPv4 packets are skipped.
+fn tcp_segment(packet: &[u8]) -> Option<(Endpoint, Endpoint, TcpHeader, &[u8])> {
+    let (source, dest, segment) = match packet.first()? >> 4 {
+        4 => {
+            let (_, header) = parse_ipv4_header(packet).ok()?;
+            if header.protocol != IPProtocol::TCP
+                || header.fragment_offset != 0
+                || header.flags & 0b001 != 0
+            {
+                return None;
+            }
+            // Short Ethernet frames are padded, and packets captured before TCP segmentation
+            // offload have a length of zero.
+            let end = match usize::from(header.length) {
+                0 => packet.len(),
+                length => length,
+            };
+            (
+                IpAddr::V4(header.source_addr),
+                IpAddr::V4(header.dest_addr),
+                packet.get(usize::from(header.ihl) * 4..end)?,
+            )
+        }
+        6 => {
+            let (payload, header) = parse_ipv6_header(packet).ok()?;
+            if header.next_header != IPProtocol::TCP {
+                return None;
+            }
+            (
+                IpAddr::V6(header.source_addr),
+                IpAddr::V6(header.dest_addr),
+                payload.get(..usize::from(header.length))?,
+            )
+        }
+        _ => return None,
+    };
+    let (payload, tcp) = parse_tcp_header(segment).ok()?;
+
+    Some((
+        (source, tcp.source_port),
+        (dest, tcp.dest_port),
+        tcp,
+        payload,
+    ))
+}
+
+/// Timestamp units per second of a PCAPNG interface, microseconds unless it says otherwise.
+fn timestamp_units(options: &[InterfaceDescriptionOption]) -> u128 {
+    options
+        .iter()
+        .find_map(|option| match option {
+            InterfaceDescriptionOption::IfTsResol(resolution) if resolution & 0x80 != 0 => {
+                2u128.checked_pow(u32::from(resolution & 0x7f))
+            }
+            InterfaceDescriptionOption::IfTsResol(resolution) => {
+                10u128.checked_pow(u32::from(*resolution))
+            }
+            _ => None,
+        })
+        .unwrap_or(1_000_000)
+}
+
+/// Reads the text messages of the WebSocket connections on one of `ports` in a PCAP or PCAPNG
+/// capture. A capture that ends in a broken packet, e.g. of an interrupted `tcpdump`, is read up
+/// to that packet.
+pub fn read_capture(data: &[u8], ports: &[u16]) -> anyhow::Result<Vec<TraceLine>> {
+    let mut capture = Capture {
+        ports,
+        connections: HashMap::new(),
+        trace_lines: Vec::new(),
+    };
+
+    if data.starts_with(&PCAPNG_MAGIC) {
+        let mut reader = PcapNgReader::new(data)?;
+        // Link type and timestamp units of the interfaces of the current section.
+        let mut interfaces = Vec::new();
+        while let Some(block) = reader.next_block() {
+            match block {
+                Ok(Block::SectionHeader(_)) => interfaces.clear(),
+                Ok(Block::InterfaceDescription(interface)) => {
+                    interfaces.push((interface.linktype, timestamp_units(&interface.options)))
+                }
+                Ok(Block::EnhancedPacket(packet)) => {
+                    let Some((datalink, units)) = interfaces.get(packet.interface_id as usize)
+                    else {
+                        continue;
+                    };
+                    // Read as nanoseconds, whatever the units are.
+                    let timestamp = packet.timestamp.as_nanos();
+                    let nanos = (timestamp % units) * 1_000_000_000 / units;
+                    let Ok(secs) = u64::try_from(timestamp / units) else {
+                        continue;
+                    };
+                    capture.add_packet(Duration::new(secs, nanos as u32), *datalink, &packet.data);
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    re_log::warn!("Stopped reading the capture at a broken block: {}", err);
+                    break;
+                }
+            }
+        }
+    } else {
+        let mut reader = PcapReader::new(data)?;
+        let datalink = reader.header().datalink;
+        while let Some(packet) = reader.next_packet() {
+            match packet {
+                Ok(packet) => capture.add_packet(packet.timestamp, datalink, &packet.data),
+                Err(err) => {
+                    re_log::warn!("Stopped reading the capture at a broken packet: {}", err);
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(capture.trace_lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_PING: u8 = 0x9;
+
+    /// WebSocket frame with `payload`, masked with `mask` if there is one.
+    fn frame(fin: bool, opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+        let mut frame = vec![u8::from(fin) << 7 | opcode];
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        match payload.len() {
+            length @ 0..=125 => frame.push(mask_bit | length as u8),
+            length @ 126..=0xffff => {
+                frame.push(mask_bit | 126);
+                frame.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                frame.push(mask_bit | 127);
+                frame.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+        match mask {
+            Some(mask) => {
+                frame.extend_from_slice(&mask);
+                frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+            }
+            None => frame.extend_from_slice(payload),
+        }
+
+        frame
+    }
+
+    fn text_frame(payload: &str) -> Vec<u8> {
+        frame(true, OPCODE_TEXT, payload.as_bytes(), None)
+    }
+
+    #[test]
+    fn out_of_order_segments_are_reassembled() {
+        let mut stream = TcpStream::new();
+        stream.add_segment(100, b"abc");
+        stream.add_segment(106, b"ghi");
+        assert_eq!(stream.buffer, b"abc");
+
+        stream.add_segment(103, b"def");
+        assert_eq!(stream.buffer, b"abcdefghi");
+        assert!(stream.out_of_order.is_empty());
+        assert_eq!(stream.next_seq, Some(109));
+    }
+
+    #[test]
+    fn retransmitted_bytes_are_dropped() {
+        let mut stream = TcpStream::new();
+        stream.add_segment(100, b"abcd");
+        // Overlapping the end of the first segment, then a retransmission of all of it.
+        stream.add_segment(102, b"cdef");
+        stream.add_segment(100, b"abcd");
+        // Ahead of a gap, and overlapping the segment that fills it.
+        stream.add_segment(108, b"ijk");
+        stream.add_segment(106, b"ghij");
+
+        assert_eq!(stream.buffer, b"abcdefghijk");
+        assert!(stream.out_of_order.is_empty());
+    }
+
+    #[test]
+    fn sequence_numbers_wrap_around() {
+        let mut stream = TcpStream::new();
+        stream.add_segment(u32::MAX - 1, b"ab");
+        stream.add_segment(2, b"ef");
+        assert_eq!(stream.buffer, b"ab");
+
+        stream.add_segment(0, b"cd");
+        assert_eq!(stream.buffer, b"abcdef");
+        assert_eq!(stream.next_seq, Some(4));
+    }
+
+    #[test]
+    fn too_many_out_of_order_segments_end_the_stream() {
+        let mut stream = TcpStream::new();
+        stream.add_segment(0, &text_frame("[2,"));
+        // Bytes 5 to 9 are never captured.
+        for segment in 0..MAX_OUT_OF_ORDER_SEGMENTS {
+            stream.add_segment(10 + segment as u32, b"x");
+        }
+        assert!(matches!(stream.stage, Stage::Handshake));
+        assert_eq!(stream.out_of_order.len(), MAX_OUT_OF_ORDER_SEGMENTS);
+
+        stream.add_segment(10 + MAX_OUT_OF_ORDER_SEGMENTS as u32, b"x");
+        assert!(matches!(stream.stage, Stage::Unreadable));
+        assert!(stream.out_of_order.is_empty());
+        assert!(stream.buffer.is_empty());
+
+        stream.add_segment(5, &text_frame("[]"));
+        assert!(stream.decode(false).is_empty());
+    }
+
+    #[test]
+    fn extended_payload_lengths() {
+        for length in [125, 126, 0xffff, 0x10000] {
+            let payload = vec![b'a'; length];
+            let data = frame(true, OPCODE_TEXT, &payload, None);
+
+            let Ok((frame, frame_length)) = parse_frame(&data, false) else {
+                panic!("Frame with {} bytes didn't parse", length);
+            };
+            assert_eq!(frame.payload, payload);
+            assert_eq!(frame_length, data.len());
+            assert!(matches!(
+                parse_frame(&data[..data.len() - 1], false),
+                Err(FrameError::Incomplete)
+            ));
+        }
+
+        // The 64-bit length is checked before waiting for the payload.
+        let mut data = vec![0x81, 127];
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert!(matches!(
+            parse_frame(&data, false),
+            Err(FrameError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn client_frames_are_unmasked() {
+        let data = frame(
+            true,
+            OPCODE_TEXT,
+            b"[2,\"h1\"]",
+            Some([0x12, 0x34, 0x56, 0x78]),
+        );
+
+        let Ok((frame, _)) = parse_frame(&data, true) else {
+            panic!("Masked frame didn't parse");
+        };
+        assert_eq!(frame.payload, b"[2,\"h1\"]");
+        // Masked frames only come from the client, unmasked ones only from the server.
+        assert!(matches!(
+            parse_frame(&data, false),
+            Err(FrameError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse_frame(&text_frame("[]"), true),
+            Err(FrameError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn reserved_bits_and_compression() {
+        let mut data = text_frame("[]");
+        data[0] |= 0x20;
+        assert!(matches!(
+            parse_frame(&data, false),
+            Err(FrameError::Invalid(_))
+        ));
+
+        let mut data = text_frame("[]");
+        data[0] |= 0x40;
+        let Ok((frame, _)) = parse_frame(&data, false) else {
+            panic!("Compressed frame didn't parse");
+        };
+        assert!(frame.compressed);
+
+        let mut stream = TcpStream::new();
+        stream.add_segment(0, &data);
+        assert!(stream.decode(false).is_empty());
+        assert!(matches!(stream.stage, Stage::Unreadable));
+    }
+
+    #[test]
+    fn fragmented_messages_are_joined() {
+        let mut data = frame(false, OPCODE_TEXT, b"[2,\"s1\",", None);
+        // Control frames may come between the fragments.
+        data.extend(frame(true, OPCODE_PING, b"", None));
+        data.extend(frame(
+            false,
+            OPCODE_CONTINUATION,
+            b"\"StatusNotification\",",
+            None,
+        ));
+        data.extend(frame(true, OPCODE_CONTINUATION, b"{}]", None));
+        // A continuation of a message from before the capture started.
+        let mut stream = TcpStream::new();
+        stream.add_segment(0, &frame(true, OPCODE_CONTINUATION, b"]", None));
+        stream.add_segment(3, &data);
+
+        assert_eq!(
+            stream.decode(false),
+            vec!["[2,\"s1\",\"StatusNotification\",{}]".to_owned()]
+        );
+        assert!(stream.message.is_none());
+    }
+
+    #[test]
+    fn handshake_is_skipped() {
+        let mut stream = TcpStream::new();
+        let mut data = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n".to_vec();
+        data.extend(text_frame("[3,\"h1\",{}]"));
+        // The upgrade response and the frame arrive in parts.
+        stream.add_segment(0, &data[..4]);
+        assert!(stream.decode(false).is_empty());
+        stream.add_segment(4, &data[4..]);
+
+        assert_eq!(stream.decode(false), vec!["[3,\"h1\",{}]".to_owned()]);
+    }
+
+    #[test]
+    fn rejected_upgrade_ends_the_stream() {
+        let mut stream = TcpStream::new();
+        let mut data = b"HTTP/1.1 404 Not Found\r\nContent-Length: 2\r\n\r\n".to_vec();
+        data.extend(text_frame("[3,\"h1\",{}]"));
+        stream.add_segment(0, &data);
+
+        assert!(stream.decode(false).is_empty());
+        assert!(matches!(stream.stage, Stage::Unreadable));
+        assert!(stream.handshake.is_none());
+    }
+
+    #[test]
+    fn station_id_from_the_upgrade_request() {
+        let mut connection = Connection {
+            to_server: TcpStream::new(),
+            to_client: TcpStream::new(),
+        };
+        connection.to_server.add_segment(
+            0,
+            b"GET /ocpp/EVSE-001/?token=1 HTTP/1.1\r\nHost: cs\r\n\r\n",
+        );
+        connection.to_server.decode(true);
+
+        assert_eq!(connection.station_id().as_deref(), Some("EVSE-001"));
+    }
+}
@@ -0,0 +1,2029 @@
+//! Plotting of the decoded messages and the log file entries to the Rerun recording.
+
+use std::collections::{HashMap, HashSet, VecDeque, hash_map::Entry};
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate};
+use itertools::Itertools;
+use rerun::{
+    AnnotationContext, RecordingStream, Scalars, SeriesLines, SeriesPoints, TextDocument, TextLog,
+    TextLogLevel, components::MarkerShape, external::re_log,
+};
+use rust_ocpp::v1_6::{
+    messages::{
+        boot_notification::BootNotificationRequest, status_notification::StatusNotificationRequest,
+    },
+    types::{
+        ChargePointErrorCode, ChargePointStatus, ConfigurationStatus, DiagnosticsStatus,
+        FirmwareStatus, Measurand, MessageTrigger, Phase, Reason, RemoteStartStopStatus,
+        ResetRequestStatus, ResetResponseStatus, TriggerMessageStatus, UnlockStatus,
+    },
+};
+
+use log_viewer::log_file::LogFileEntry;
+use log_viewer::ocpp::{
+    LINE_PHASES, MeterValuesBatch, OcppMessage, SampledReading, TraceFileEntry, ocpp_name,
+    phase_value, total_power_active_import,
+};
+
+use crate::blueprint::MeterRoot;
+use crate::theme::ThemeMap;
+
+/// Entity path a measurand is logged to, without the phase suffix.
+pub fn measurand_entity_path(measurand: &Measurand) -> &'static str {
+    match measurand {
+        Measurand::CurrentExport => "current/export",
+        Measurand::CurrentImport => "current/import",
+        Measurand::CurrentOffered => "current/offered",
+        Measurand::EnergyActiveExportRegister => "energy/active/export/register",
+        Measurand::EnergyActiveImportRegister => "energy/active/import/register",
+        Measurand::EnergyReactiveExportRegister => "energy/reactive/export/register",
+        Measurand::EnergyReactiveImportRegister => "energy/reactive/import/register",
+        Measurand::EnergyActiveExportInterval => "energy/active/export/interval",
+        Measurand::EnergyActiveImportInterval => "energy/active/import/interval",
+        Measurand::EnergyReactiveExportInterval => "energy/reactive/export/interval",
+        Measurand::EnergyReactiveImportInterval => "energy/reactive/import/interval",
+        Measurand::Frequency => "grid/frequency_hz",
+        Measurand::PowerActiveExport => "power/active/export",
+        Measurand::PowerActiveImport => "power/active/import",
+        Measurand::PowerFactor => "power/factor",
+        Measurand::PowerOffered => "power/offered",
+        Measurand::PowerReactiveExport => "power/reactive/export",
+        Measurand::PowerReactiveImport => "power/reactive/import",
+        Measurand::Rpm => "cooling/fan_rpm",
+        Measurand::SoC => "battery/soc",
+        Measurand::Temperature => "temperature",
+        Measurand::Voltage => "voltage",
+    }
+}
+
+/// Entity path segment of a phase below its measurand, e.g. `voltage/L1N` for a line-to-neutral
+/// and `voltage/L12` for a line-to-line voltage.
+pub fn phase_to_path_segment(phase: &Phase) -> &'static str {
+    match phase {
+        Phase::L1 => "L1",
+        Phase::L2 => "L2",
+        Phase::L3 => "L3",
+        Phase::N => "N",
+        Phase::L1N => "L1N",
+        Phase::L2N => "L2N",
+        Phase::L3N => "L3N",
+        Phase::L1L2 => "L12",
+        Phase::L2L3 => "L23",
+        Phase::L3L1 => "L31",
+    }
+}
+
+pub fn plot_pv_production(rec: &RecordingStream, root: &str, value: f64) -> anyhow::Result<()> {
+    rec.log(
+        entity_path(root, "log/pv_production"),
+        &Scalars::single(value),
+    )?;
+
+    Ok(())
+}
+
+pub fn plot_battery_load(rec: &RecordingStream, root: &str, value: f64) -> anyhow::Result<()> {
+    rec.log(
+        entity_path(root, "log/battery_load"),
+        &Scalars::single(value),
+    )?;
+
+    Ok(())
+}
+
+pub fn plot_ev_import(rec: &RecordingStream, root: &str, value: f64) -> anyhow::Result<()> {
+    rec.log(entity_path(root, "log/ev_import"), &Scalars::single(value))?;
+
+    Ok(())
+}
+
+pub fn plot_load_overall(rec: &RecordingStream, root: &str, value: f64) -> anyhow::Result<()> {
+    rec.log(
+        entity_path(root, "log/load_overall"),
+        &Scalars::single(value),
+    )?;
+
+    Ok(())
+}
+
+pub fn plot_overproduction(rec: &RecordingStream, root: &str, value: f64) -> anyhow::Result<()> {
+    rec.log(
+        entity_path(root, "log/overproduction"),
+        &Scalars::single(value),
+    )?;
+
+    Ok(())
+}
+
+pub const SERIES_WIDTH: f32 = 2.0;
+
+/// Color, name and width of the series of `measurand` on `phase`, named by their OCPP names, e.g.
+/// `Current.Import L1`.
+pub fn series_style(theme: &ThemeMap, measurand: &Measurand, phase: &Option<Phase>) -> SeriesLines {
+    SeriesLines::new()
+        .with_colors([theme.color(measurand, phase)])
+        .with_names([series_name(measurand, phase)])
+        .with_widths([SERIES_WIDTH])
+}
+
+pub fn series_name(measurand: &Measurand, phase: &Option<Phase>) -> String {
+    match (measurand, phase) {
+        (_, Some(phase)) => format!("{} {}", ocpp_name(measurand), ocpp_name(phase)),
+        // The only percentage among the measurands.
+        (Measurand::SoC, None) => "SoC (%)".to_owned(),
+        (_, None) => ocpp_name(measurand),
+    }
+}
+
+/// Entity path of the series of a reading, below its measurand and, if it has one, its phase.
+/// Temperatures without a phase go to `temperature/total`, next to the ones where the phase tells
+/// the sensors apart.
+pub fn reading_entity_path(root: &str, reading: &SampledReading) -> String {
+    let measurand_path = measurand_entity_path(&reading.measurand);
+    let path = match &reading.phase {
+        Some(phase) => format!("{}/{}", measurand_path, phase_to_path_segment(phase)),
+        None if reading.measurand == Measurand::Temperature => {
+            format!("{}/total", measurand_path)
+        }
+        None => measurand_path.to_owned(),
+    };
+
+    entity_path(root, &path)
+}
+
+/// Blue at 0 °C, turning red towards `max_c`.
+/// Whether `--derivative` plots the rate of change of the measurand: the registers and the
+/// exported values.
+fn has_derivative(measurand: &Measurand) -> bool {
+    matches!(
+        measurand,
+        Measurand::EnergyActiveExportRegister
+            | Measurand::EnergyActiveImportRegister
+            | Measurand::EnergyReactiveExportRegister
+            | Measurand::EnergyReactiveImportRegister
+            | Measurand::EnergyActiveExportInterval
+            | Measurand::EnergyReactiveExportInterval
+            | Measurand::CurrentExport
+            | Measurand::PowerActiveExport
+            | Measurand::PowerReactiveExport
+    )
+}
+
+/// Whether the measurand is an energy register or interval, in Wh or varh.
+fn is_energy(measurand: &Measurand) -> bool {
+    matches!(
+        measurand,
+        Measurand::EnergyActiveExportRegister
+            | Measurand::EnergyActiveImportRegister
+            | Measurand::EnergyReactiveExportRegister
+            | Measurand::EnergyReactiveImportRegister
+            | Measurand::EnergyActiveExportInterval
+            | Measurand::EnergyActiveImportInterval
+            | Measurand::EnergyReactiveExportInterval
+            | Measurand::EnergyReactiveImportInterval
+    )
+}
+
+fn temperature_color(value_c: f64, max_c: f64) -> [u8; 3] {
+    let t = (value_c / max_c).clamp(0.0, 1.0);
+    let lerp = |cold: u8, hot: u8| (cold as f64 + (hot as f64 - cold as f64) * t).round() as u8;
+
+    [lerp(0, 255), lerp(100, 0), lerp(255, 0)]
+}
+
+/// Joins `path` onto the entity `root`, e.g. the station ID, or returns it as is without a root.
+pub fn entity_path(root: &str, path: &str) -> String {
+    if root.is_empty() {
+        path.to_owned()
+    } else {
+        format!("{}/{}", root, path)
+    }
+}
+
+/// Plots OCPP messages and remembers which entities already got their series style logged.
+pub struct TracePlotter {
+    rec: RecordingStream,
+    styled_entities: HashSet<String>,
+    /// Integrated import energy, keyed by entity path.
+    energy_accumulators: HashMap<String, EnergyAccumulator>,
+    /// Last seen transaction ID per entity root, to restart the energy integration on a new one.
+    transaction_ids: HashMap<String, String>,
+    /// Timestamp of the previous heartbeat per entity root.
+    last_heartbeats: HashMap<String, f64>,
+    /// Firmware version of the last BootNotification per entity root.
+    firmware_versions: HashMap<String, Option<String>>,
+    /// Entity roots that installed a firmware since their last BootNotification.
+    installed_firmware: HashSet<String>,
+    /// Time of the accepted RemoteStartTransaction calls that no transaction was started for yet,
+    /// keyed by entity root and idTag.
+    remote_starts: HashMap<(String, String), f64>,
+    /// Like `remote_starts` for RemoteStopTransaction, keyed by entity root and transaction ID.
+    remote_stops: HashMap<(String, i32), f64>,
+    /// Number of invalid readings so far, keyed by entity path.
+    parse_errors: HashMap<String, u64>,
+    /// Number of CallErrors so far, keyed by the entity path of the count.
+    call_errors: HashMap<String, u64>,
+    /// Number of UnlockConnector calls so far, keyed by the entity path of the count.
+    unlock_attempts: HashMap<String, u64>,
+    /// Keyed by the entity path of the raw series.
+    rolling_averages: HashMap<String, RollingAverage>,
+    /// Values logged so far with `--sample-rate`, keyed by entity path.
+    sample_counts: HashMap<String, u64>,
+    /// Time of the values being logged, see [`TracePlotter::set_time`].
+    timestamp_s: f64,
+    /// Hash of the time, entity path and value of the last value logged, keyed by entity path, to
+    /// skip the values of retransmitted messages logged twice.
+    last_samples: HashMap<String, u64>,
+    /// Number of values that were skipped as duplicates.
+    duplicates: u64,
+    /// Timestamp and value of the last reading, keyed by entity path, for the derivatives.
+    previous_readings: HashMap<String, (f64, f64)>,
+    /// Peak power of the running transactions, keyed by station and transaction ID.
+    peak_powers: HashMap<(String, String), SessionPeak>,
+    /// Station, transaction ID and peak power of the finished transactions.
+    session_peaks: Vec<(String, String, f64)>,
+    /// Time spent per connector status so far, keyed by the entity path of the connector.
+    connector_availabilities: HashMap<String, ConnectorAvailability>,
+    /// Layout of the entry being plotted, on its date.
+    layout: PathLayout,
+    config: PlotConfig,
+}
+
+/// Unit of each DataTransfer field that is plotted, keyed by vendor ID and field name.
+pub type DataTransferSchema = HashMap<String, HashMap<String, String>>;
+
+pub fn load_data_transfer_schema(path: &Path) -> anyhow::Result<DataTransferSchema> {
+    let file = File::open(path)
+        .with_context(|| format!("Could not read DataTransfer schema `{}`", path.display()))?;
+
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Could not parse DataTransfer schema `{}`", path.display()))
+}
+
+/// Settings that decide where and how the messages are plotted.
+#[derive(Clone)]
+pub struct PlotConfig {
+    pub heartbeat_alarm_threshold_s: f64,
+    /// Also log the average of every sampled value series over this many seconds.
+    pub rolling_avg_window_s: Option<u64>,
+    /// Only log every Nth value of the meter value series.
+    pub sample_rate: Option<u64>,
+    /// Also log the derivative of register and export measurands.
+    pub derivative: bool,
+    /// Relative spread of the phase currents above which they count as imbalanced.
+    pub imbalance_threshold: f64,
+    pub nominal_voltage_v: f64,
+    /// Deviation from the nominal voltage in percent above which a voltage is flagged.
+    pub voltage_tolerance_pct: f64,
+    /// Temperature above which a temperature is flagged, the red end of its color scale.
+    pub temperature_max_c: f64,
+    pub nominal_frequency_hz: f64,
+    /// Deviation from the nominal frequency above which a frequency is flagged.
+    pub frequency_tolerance_hz: f64,
+    /// Fan speed below which the fan counts as stalled while charging.
+    pub rpm_stall_threshold: f64,
+    /// Total power factor below which it's flagged.
+    pub power_factor_min_alert: f64,
+    pub data_transfer_schema: DataTransferSchema,
+    pub layout: PathLayout,
+    pub theme: ThemeMap,
+}
+
+/// Where in the entity tree the messages of a station, connector and transaction are put.
+#[derive(Clone, Default)]
+pub struct PathLayout {
+    /// Root of all entities, may be empty.
+    pub prefix: String,
+    /// Put the entities below the date of their message, see [`PathLayout::on_date`].
+    pub group_by_date: bool,
+    /// Date below the prefix, if grouped by date.
+    pub date: Option<NaiveDate>,
+    /// Put everything directly below the station ID instead of into the
+    /// `station/{station_id}/connector/{connector_id}/tx/{transaction_id}` hierarchy.
+    pub flat: bool,
+    /// Put flat meter values below `transaction/{transaction_id}`.
+    pub transaction_prefix: bool,
+}
+
+impl PathLayout {
+    /// Layout of the messages of `date`, which are put below `{date}`, e.g. `2024-01-15`, when
+    /// grouping by date.
+    pub fn on_date(&self, date: NaiveDate) -> Self {
+        Self {
+            date: Some(date).filter(|_| self.group_by_date),
+            ..self.clone()
+        }
+    }
+
+    /// Entity root of the station-level entities of `station_id`, which may be empty.
+    pub fn station(&self, station_id: &str) -> String {
+        self.prefixed(self.station_path(station_id))
+    }
+
+    fn station_path(&self, station_id: &str) -> String {
+        if self.flat || station_id.is_empty() {
+            station_id.to_owned()
+        } else {
+            entity_path("station", station_id)
+        }
+    }
+
+    fn prefixed(&self, path: String) -> String {
+        let prefix = match self.date {
+            Some(date) => entity_path(&self.prefix, &date.to_string()),
+            None => self.prefix.clone(),
+        };
+
+        if path.is_empty() {
+            prefix
+        } else {
+            entity_path(&prefix, &path)
+        }
+    }
+
+    pub fn meter_values(
+        &self,
+        station_id: &str,
+        connector_id: Option<u32>,
+        transaction_id: Option<&str>,
+    ) -> String {
+        let mut path = self.station_path(station_id);
+
+        if self.flat {
+            if let Some(transaction_id) = transaction_id.filter(|_| self.transaction_prefix) {
+                path = entity_path(&format!("transaction/{}", transaction_id), &path);
+            }
+        } else {
+            if let Some(connector_id) = connector_id {
+                path = entity_path(&path, &format!("connector/{}", connector_id));
+            }
+            if let Some(transaction_id) = transaction_id {
+                path = entity_path(&path, &format!("tx/{}", transaction_id));
+            }
+        }
+
+        self.prefixed(path)
+    }
+}
+
+/// Running trapezoidal integration of a power series.
+pub struct EnergyAccumulator {
+    pub last_timestamp_s: f64,
+    pub last_power_w: f64,
+    pub energy_wh: f64,
+}
+
+impl EnergyAccumulator {
+    pub fn new(timestamp_s: f64, power_w: f64) -> Self {
+        Self {
+            last_timestamp_s: timestamp_s,
+            last_power_w: power_w,
+            energy_wh: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, timestamp_s: f64, power_w: f64) {
+        let delta_hours = (timestamp_s - self.last_timestamp_s) / 3600.0;
+        self.energy_wh += (self.last_power_w + power_w) / 2.0 * delta_hours;
+        self.last_timestamp_s = timestamp_s;
+        self.last_power_w = power_w;
+    }
+}
+
+/// Highest total import power of a transaction so far.
+pub struct SessionPeak {
+    /// Entity the peak is logged to once the transaction stopped.
+    pub path: String,
+    pub power_w: f64,
+}
+
+/// Time a connector spent in its statuses, from its StatusNotifications.
+pub struct ConnectorAvailability {
+    pub status: ChargePointStatus,
+    /// Time of the StatusNotification that entered `status`.
+    pub since_s: f64,
+    pub total_s: f64,
+    /// Outside of Faulted and Unavailable.
+    pub uptime_s: f64,
+    pub charging_s: f64,
+    pub fault_s: f64,
+}
+
+impl ConnectorAvailability {
+    fn new(status: ChargePointStatus, timestamp_s: f64) -> Self {
+        Self {
+            status,
+            since_s: timestamp_s,
+            total_s: 0.0,
+            uptime_s: 0.0,
+            charging_s: 0.0,
+            fault_s: 0.0,
+        }
+    }
+
+    /// Adds the time since the last transition to the current status and enters `status`.
+    fn transition(&mut self, status: ChargePointStatus, timestamp_s: f64) {
+        let duration_s = (timestamp_s - self.since_s).max(0.0);
+        self.total_s += duration_s;
+        match self.status {
+            ChargePointStatus::Faulted => self.fault_s += duration_s,
+            ChargePointStatus::Unavailable => {}
+            ChargePointStatus::Charging => {
+                self.uptime_s += duration_s;
+                self.charging_s += duration_s;
+            }
+            _ => self.uptime_s += duration_s,
+        }
+        self.status = status;
+        self.since_s = timestamp_s;
+    }
+
+    fn percentage(&self, duration_s: f64) -> f64 {
+        duration_s / self.total_s * 100.0
+    }
+}
+
+/// Mean of the values of a series within a trailing time window.
+#[derive(Default)]
+pub struct RollingAverage {
+    pub values: VecDeque<(f64, f64)>,
+}
+
+impl RollingAverage {
+    fn add(&mut self, timestamp_s: f64, value: f64, window_s: f64) -> f64 {
+        self.values.push_back((timestamp_s, value));
+        while self
+            .values
+            .front()
+            .is_some_and(|(oldest_s, _)| timestamp_s - oldest_s > window_s)
+        {
+            self.values.pop_front();
+        }
+
+        self.values.iter().map(|(_, value)| value).sum::<f64>() / self.values.len() as f64
+    }
+}
+
+impl TracePlotter {
+    pub fn new(rec: &RecordingStream, config: PlotConfig) -> Self {
+        Self {
+            rec: rec.clone(),
+            styled_entities: HashSet::new(),
+            energy_accumulators: HashMap::new(),
+            transaction_ids: HashMap::new(),
+            last_heartbeats: HashMap::new(),
+            firmware_versions: HashMap::new(),
+            installed_firmware: HashSet::new(),
+            remote_starts: HashMap::new(),
+            remote_stops: HashMap::new(),
+            parse_errors: HashMap::new(),
+            call_errors: HashMap::new(),
+            unlock_attempts: HashMap::new(),
+            rolling_averages: HashMap::new(),
+            sample_counts: HashMap::new(),
+            timestamp_s: 0.0,
+            last_samples: HashMap::new(),
+            duplicates: 0,
+            previous_readings: HashMap::new(),
+            peak_powers: HashMap::new(),
+            session_peaks: Vec::new(),
+            connector_availabilities: HashMap::new(),
+            layout: config.layout.clone(),
+            config,
+        }
+    }
+
+    /// Plots the reading and the series derived from it, and returns whether it was plotted,
+    /// which a duplicate isn't.
+    pub fn plot_sampled_reading(
+        &mut self,
+        root: &str,
+        timestamp_s: f64,
+        reading: &SampledReading,
+    ) -> anyhow::Result<bool> {
+        let entity_path = reading_entity_path(root, reading);
+        // Before the rolling average and the derivative, which the value is added to.
+        if self.is_duplicate(&entity_path, reading.value) {
+            return Ok(false);
+        }
+
+        if reading.measurand == Measurand::Temperature {
+            // Colored by value in `plot_temperature`, which a static color would override.
+            self.style_series(&entity_path, |_| {
+                SeriesLines::new()
+                    .with_names([series_name(&reading.measurand, &reading.phase)])
+                    .with_widths([SERIES_WIDTH])
+            })?;
+        } else {
+            self.style_series(&entity_path, |theme| {
+                series_style(theme, &reading.measurand, &reading.phase)
+            })?;
+        }
+        self.log_nth_sample(&entity_path, reading.value)?;
+        if reading.measurand == Measurand::SoC {
+            self.plot_soc_target(root)?;
+        }
+
+        if let Some(window_s) = self.config.rolling_avg_window_s {
+            let average = self
+                .rolling_averages
+                .entry(entity_path.clone())
+                .or_default()
+                .add(timestamp_s, reading.value, window_s as f64);
+            let average_path = format!("{}/avg{}s", entity_path, window_s);
+            self.style_series(&average_path, |theme| {
+                series_style(theme, &reading.measurand, &reading.phase).with_names([format!(
+                    "{} ({} s average)",
+                    series_name(&reading.measurand, &reading.phase),
+                    window_s
+                )])
+            })?;
+            self.log_sample(&average_path, average)?;
+        }
+
+        if self.config.derivative && has_derivative(&reading.measurand) {
+            self.plot_derivative(&entity_path, timestamp_s, reading)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Logs the rate of change since the previous reading of `entity_path`, per hour for energy
+    /// so that Wh turn into W, and per second otherwise.
+    fn plot_derivative(
+        &mut self,
+        entity_path: &str,
+        timestamp_s: f64,
+        reading: &SampledReading,
+    ) -> anyhow::Result<()> {
+        let previous = self.previous_readings.get(entity_path).copied();
+        // Readings with the same timestamp have no rate of change, keep the older one to compare the
+        // next reading against.
+        if previous.is_some_and(|(previous_s, _)| previous_s == timestamp_s) {
+            return Ok(());
+        }
+        self.previous_readings
+            .insert(entity_path.to_owned(), (timestamp_s, reading.value));
+        let Some((previous_s, previous_value)) = previous else {
+            return Ok(());
+        };
+
+        let per_second = (reading.value - previous_value) / (timestamp_s - previous_s);
+        let derivative = if is_energy(&reading.measurand) {
+            per_second * 3600.0
+        } else {
+            per_second
+        };
+
+        let derivative_path = format!("{}/derivative", entity_path);
+        self.style_series(&derivative_path, |theme| {
+            series_style(theme, &reading.measurand, &reading.phase).with_names([format!(
+                "{} (derivative)",
+                series_name(&reading.measurand, &reading.phase)
+            )])
+        })?;
+        self.log_sample(&derivative_path, derivative)?;
+
+        Ok(())
+    }
+
+    /// Sets the time of the values logged from now on.
+    pub fn set_time(&mut self, timestamp_s: f64) {
+        self.rec.set_timestamp_secs_since_epoch("time", timestamp_s);
+        self.timestamp_s = timestamp_s;
+    }
+
+    /// Logs the 80 % many EVs stop fast charging at next to every SoC value, which draws it as a
+    /// reference line over the time range of the SoC series.
+    fn plot_soc_target(&mut self, root: &str) -> anyhow::Result<()> {
+        let entity_path = entity_path(root, "battery/soc_target");
+        self.style_series(&entity_path, |_| {
+            SeriesLines::new()
+                .with_colors([[128, 128, 128]])
+                .with_names(["80 % SoC"])
+                .with_widths([1.0])
+        })?;
+        self.rec.log(entity_path, &Scalars::single(80.0))?;
+
+        Ok(())
+    }
+
+    /// Whether `value` is the same as the last one of `entity_path` at the same time, like the
+    /// values of retransmitted messages, which are counted and skipped.
+    fn is_duplicate(&mut self, entity_path: &str, value: f64) -> bool {
+        let mut hasher = DefaultHasher::new();
+        (self.timestamp_s.to_bits(), entity_path, value.to_bits()).hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.last_samples.insert(entity_path.to_owned(), hash) == Some(hash) {
+            self.duplicates += 1;
+            return true;
+        }
+
+        false
+    }
+
+    /// Logs `value` to `entity_path` with [`TracePlotter::log_nth_sample`], unless it is a
+    /// duplicate.
+    fn log_sample(&mut self, entity_path: &str, value: f64) -> anyhow::Result<()> {
+        if self.is_duplicate(entity_path, value) {
+            return Ok(());
+        }
+
+        self.log_nth_sample(entity_path, value)
+    }
+
+    /// Logs `value` to `entity_path`, or with `--sample-rate N` only the first and every Nth value
+    /// after it.
+    fn log_nth_sample(&mut self, entity_path: &str, value: f64) -> anyhow::Result<()> {
+        if let Some(sample_rate) = self.config.sample_rate {
+            let count = self
+                .sample_counts
+                .entry(entity_path.to_owned())
+                .or_default();
+            let skip = !count.is_multiple_of(sample_rate);
+            *count += 1;
+            if skip {
+                return Ok(());
+            }
+        }
+
+        self.rec.log(entity_path, &Scalars::single(value))?;
+
+        Ok(())
+    }
+
+    /// Logs the style of the series at `entity_path` the first time it is plotted.
+    fn style_series(
+        &mut self,
+        entity_path: &str,
+        style: impl FnOnce(&ThemeMap) -> SeriesLines,
+    ) -> anyhow::Result<()> {
+        if self.styled_entities.insert(entity_path.to_owned()) {
+            self.rec
+                .log_static(entity_path, &style(&self.config.theme))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn plot_power_active_import_sum(&mut self, root: &str, value: f64) -> anyhow::Result<()> {
+        let entity_path = entity_path(root, "power/active/import/sum");
+        self.style_series(&entity_path, |theme| {
+            series_style(theme, &Measurand::PowerActiveImport, &None)
+                .with_names(["Power.Active.Import sum"])
+        })?;
+        self.log_sample(&entity_path, value)?;
+
+        Ok(())
+    }
+
+    /// Integrates `power_w` of `phase`, or of all phases without one, into its accumulator and
+    /// logs the energy so far in kWh.
+    fn plot_integrated_energy(
+        &mut self,
+        root: &str,
+        phase: Option<&Phase>,
+        timestamp_s: f64,
+        power_w: f64,
+    ) -> anyhow::Result<()> {
+        let (path, name) = match phase {
+            Some(phase) => (
+                format!("energy/integrated/import/{}", phase_to_path_segment(phase)),
+                format!("Integrated energy {}", ocpp_name(phase)),
+            ),
+            None => (
+                "energy/integrated/import/sum".to_owned(),
+                "Integrated energy sum".to_owned(),
+            ),
+        };
+        let entity_path = entity_path(root, &path);
+        self.style_series(&entity_path, |theme| {
+            series_style(
+                theme,
+                &Measurand::EnergyActiveImportRegister,
+                &phase.cloned(),
+            )
+            .with_names([name])
+        })?;
+        let accumulator = self
+            .energy_accumulators
+            .entry(entity_path.clone())
+            .or_insert_with(|| EnergyAccumulator::new(timestamp_s, power_w));
+        accumulator.add(timestamp_s, power_w);
+
+        let energy_kwh = accumulator.energy_wh / 1000.0;
+        self.log_sample(&entity_path, energy_kwh)?;
+
+        Ok(())
+    }
+
+    /// Restarts the energy integration and the sampling of `root` when a different transaction ID
+    /// shows up. The sampling of an empty root keeps going.
+    fn track_transaction(&mut self, root: &str, transaction_id: &str) {
+        if self.transaction_ids.get(root).map(String::as_str) == Some(transaction_id) {
+            return;
+        }
+
+        let prefix = entity_path(root, "energy/integrated/");
+        self.energy_accumulators
+            .retain(|entity_path, _| !entity_path.starts_with(&prefix));
+        // Without a root, like with `--flat-paths` and no station ID, every entity path would
+        // match, including the ones of the other connectors.
+        if !root.is_empty() {
+            let prefix = format!("{}/", root);
+            self.sample_counts
+                .retain(|entity_path, _| !entity_path.starts_with(&prefix));
+        }
+        self.transaction_ids
+            .insert(root.to_owned(), transaction_id.to_owned());
+    }
+
+    fn plot_meter_values(
+        &mut self,
+        station_id: &str,
+        root: &str,
+        timestamp_s: f64,
+        meter_values: &MeterValuesBatch,
+    ) -> anyhow::Result<()> {
+        let readings = &meter_values.readings;
+        if let Some(transaction_id) = &meter_values.transaction_id {
+            self.track_transaction(root, transaction_id);
+        }
+
+        let mut plotted = false;
+        for reading in readings {
+            plotted |= self.plot_sampled_reading(root, timestamp_s, reading)?;
+        }
+        // A retransmitted message, whose alerts and diagnostics are logged already.
+        if !readings.is_empty() && !plotted {
+            return Ok(());
+        }
+
+        for measurand in &meter_values.invalid_readings {
+            let entity_path = entity_path(
+                root,
+                &format!("parse_errors/{}", measurand_entity_path(measurand)),
+            );
+            // Counted once per message without valid readings either.
+            if readings.is_empty() && self.is_duplicate(&entity_path, 0.0) {
+                continue;
+            }
+            let count = self.parse_errors.entry(entity_path.clone()).or_default();
+            *count += 1;
+
+            self.rec.log(entity_path, &Scalars::single(*count as f64))?;
+        }
+
+        let power_active_import = readings
+            .iter()
+            .filter(|reading| {
+                reading.measurand == Measurand::PowerActiveImport
+                    && matches!(reading.phase, Some(Phase::L1 | Phase::L2 | Phase::L3))
+            })
+            .map(|reading| reading.value)
+            .collect::<Vec<_>>();
+
+        if !power_active_import.is_empty() {
+            self.plot_power_active_import_sum(root, power_active_import.iter().sum())?;
+        }
+
+        for phase in &LINE_PHASES {
+            if let Some(power) = phase_value(readings, &Measurand::PowerActiveImport, phase) {
+                self.plot_integrated_energy(root, Some(phase), timestamp_s, power)?;
+            }
+        }
+
+        if let Some(total_power) = total_power_active_import(readings) {
+            self.plot_integrated_energy(root, None, timestamp_s, total_power)?;
+
+            if let Some(transaction_id) = &meter_values.transaction_id {
+                let peak = self
+                    .peak_powers
+                    .entry((station_id.to_owned(), transaction_id.clone()))
+                    .or_insert_with(|| SessionPeak {
+                        path: entity_path(root, "peak_power_w"),
+                        power_w: total_power,
+                    });
+                peak.power_w = peak.power_w.max(total_power);
+            }
+        }
+
+        for phase in &LINE_PHASES {
+            let voltage = phase_value(readings, &Measurand::Voltage, phase);
+            let current = phase_value(readings, &Measurand::CurrentImport, phase);
+            let power = phase_value(readings, &Measurand::PowerActiveImport, phase);
+
+            if let (Some(voltage), Some(current), Some(power)) = (voltage, current, power)
+                && voltage != 0.0
+                && current != 0.0
+            {
+                self.plot_derived_power_factor(root, phase, power / (voltage * current))?;
+            }
+        }
+
+        let line_powers = LINE_PHASES
+            .iter()
+            .map(|phase| {
+                let value = |measurand| phase_value(readings, measurand, phase);
+                // Only with the voltage and current of every phase, like the per-phase power
+                // factors.
+                value(&Measurand::Voltage)?;
+                value(&Measurand::CurrentImport)?;
+                Some((
+                    value(&Measurand::PowerActiveImport)?,
+                    value(&Measurand::PowerReactiveImport)?,
+                ))
+            })
+            .collect::<Option<Vec<_>>>();
+        if let Some(line_powers) = line_powers {
+            let (active, reactive) = line_powers
+                .iter()
+                .fold((0.0, 0.0), |(p, q), (active, reactive)| {
+                    (p + active, q + reactive)
+                });
+            self.plot_total_power_factor(root, reactive.atan2(active).cos())?;
+        }
+
+        for reading in readings
+            .iter()
+            .filter(|reading| reading.measurand == Measurand::Voltage)
+        {
+            self.plot_voltage_deviation(root, reading)?;
+        }
+
+        for reading in readings
+            .iter()
+            .filter(|reading| reading.measurand == Measurand::Temperature)
+        {
+            self.plot_temperature(root, reading)?;
+        }
+
+        for reading in readings
+            .iter()
+            .filter(|reading| reading.measurand == Measurand::Frequency)
+        {
+            self.plot_frequency_deviation(root, timestamp_s, reading)?;
+        }
+
+        if total_power_active_import(readings).is_some_and(|power_w| power_w > 0.0) {
+            for reading in readings
+                .iter()
+                .filter(|reading| reading.measurand == Measurand::Rpm)
+            {
+                self.plot_fan_stall(root, reading)?;
+            }
+        }
+
+        let currents = LINE_PHASES
+            .iter()
+            .map(|phase| phase_value(readings, &Measurand::CurrentImport, phase))
+            .collect::<Option<Vec<_>>>();
+        if let Some(currents) = currents {
+            self.plot_phase_imbalance(root, &currents)?;
+        }
+
+        let mut apparent_power = Vec::new();
+        for phase in &LINE_PHASES {
+            let active = phase_value(readings, &Measurand::PowerActiveImport, phase);
+            let reactive = phase_value(readings, &Measurand::PowerReactiveImport, phase);
+
+            if let (Some(active), Some(reactive)) = (active, reactive) {
+                let value = active.hypot(reactive);
+                let entity_path = entity_path(
+                    root,
+                    &format!("power/apparent/import/{}", phase_to_path_segment(phase)),
+                );
+                self.style_series(&entity_path, |theme| {
+                    series_style(theme, &Measurand::PowerActiveImport, &Some(phase.clone()))
+                        .with_names([format!("Apparent power {}", ocpp_name(phase))])
+                })?;
+                self.log_sample(&entity_path, value)?;
+                apparent_power.push(value);
+            }
+        }
+
+        if !apparent_power.is_empty() {
+            let entity_path = entity_path(root, "power/apparent/import/sum");
+            self.style_series(&entity_path, |theme| {
+                series_style(theme, &Measurand::PowerActiveImport, &None)
+                    .with_names(["Apparent power sum"])
+            })?;
+            self.log_sample(&entity_path, apparent_power.iter().sum::<f64>())?;
+        }
+
+        Ok(())
+    }
+
+    /// Flags a voltage reading that deviates from the nominal voltage by more than the tolerance.
+    /// Voltages between two lines are compared against the nominal line-to-line voltage.
+    fn plot_voltage_deviation(&self, root: &str, reading: &SampledReading) -> anyhow::Result<()> {
+        let nominal_v = match reading.phase {
+            Some(Phase::L1L2 | Phase::L2L3 | Phase::L3L1) => {
+                self.config.nominal_voltage_v * 3.0_f64.sqrt()
+            }
+            _ => self.config.nominal_voltage_v,
+        };
+        let phase = reading
+            .phase
+            .as_ref()
+            .map_or("total", phase_to_path_segment);
+        let deviation = (reading.value - nominal_v) / nominal_v;
+        let out_of_range = deviation.abs() * 100.0 > self.config.voltage_tolerance_pct;
+
+        if out_of_range {
+            self.rec.log(
+                entity_path(root, &format!("alerts/voltage/{}", phase)),
+                &TextLog::new(format!(
+                    "{} on {}: {} V, nominal {:.0} V ({:+.1} %)",
+                    if deviation > 0.0 {
+                        "Over-voltage"
+                    } else {
+                        "Under-voltage"
+                    },
+                    phase,
+                    reading.value,
+                    nominal_v,
+                    deviation * 100.0
+                ))
+                .with_level(TextLogLevel::WARN),
+            )?;
+        }
+        self.rec.log(
+            entity_path(root, &format!("diagnostics/voltage_out_of_range/{}", phase)),
+            &Scalars::single(if out_of_range { 1.0 } else { 0.0 }),
+        )?;
+
+        Ok(())
+    }
+
+    /// Flags grid frequencies further from the nominal frequency than the tolerance.
+    fn plot_frequency_deviation(
+        &self,
+        root: &str,
+        timestamp_s: f64,
+        reading: &SampledReading,
+    ) -> anyhow::Result<()> {
+        let deviation_hz = reading.value - self.config.nominal_frequency_hz;
+        if deviation_hz.abs() <= self.config.frequency_tolerance_hz {
+            return Ok(());
+        }
+
+        let timestamp = DateTime::from_timestamp_millis((timestamp_s * 1000.0) as i64)
+            .map(|timestamp| timestamp.to_rfc3339())
+            .unwrap_or_default();
+        self.rec.log(
+            entity_path(root, "alerts/frequency_deviation"),
+            &TextLog::new(format!(
+                "Grid frequency of {} Hz at {}, {:+.2} Hz from the nominal {} Hz",
+                reading.value, timestamp, deviation_hz, self.config.nominal_frequency_hz
+            ))
+            .with_level(TextLogLevel::WARN),
+        )?;
+
+        Ok(())
+    }
+
+    /// Flags a fan speed below the stall threshold, for readings taken while charging.
+    fn plot_fan_stall(&self, root: &str, reading: &SampledReading) -> anyhow::Result<()> {
+        if reading.value >= self.config.rpm_stall_threshold {
+            return Ok(());
+        }
+
+        self.rec.log(
+            entity_path(root, "alerts/fan_stall"),
+            &TextLog::new(format!(
+                "Fan at {} RPM while charging, below {} RPM",
+                reading.value, self.config.rpm_stall_threshold
+            ))
+            .with_level(TextLogLevel::WARN),
+        )?;
+
+        Ok(())
+    }
+
+    /// Colors the temperature series by value and flags temperatures above the maximum.
+    fn plot_temperature(&self, root: &str, reading: &SampledReading) -> anyhow::Result<()> {
+        let max_c = self.config.temperature_max_c;
+        self.rec.log(
+            reading_entity_path(root, reading),
+            &SeriesLines::update_fields().with_colors([temperature_color(reading.value, max_c)]),
+        )?;
+
+        let sensor = reading
+            .phase
+            .as_ref()
+            .map_or("total", phase_to_path_segment);
+        let too_hot = reading.value > max_c;
+        if too_hot {
+            self.rec.log(
+                entity_path(root, &format!("alerts/temperature/{}", sensor)),
+                &TextLog::new(format!(
+                    "Temperature of {} at {} °C, above {} °C",
+                    sensor, reading.value, max_c
+                ))
+                .with_level(TextLogLevel::WARN),
+            )?;
+        }
+        self.rec.log(
+            entity_path(
+                root,
+                &format!("diagnostics/temperature_too_high/{}", sensor),
+            ),
+            &Scalars::single(if too_hot { 1.0 } else { 0.0 }),
+        )?;
+
+        Ok(())
+    }
+
+    /// Flags the import currents of L1, L2 and L3 as imbalanced if their spread relative to the
+    /// largest one exceeds the threshold.
+    fn plot_phase_imbalance(&self, root: &str, currents: &[f64]) -> anyhow::Result<()> {
+        let max = currents.iter().copied().fold(f64::MIN, f64::max);
+        let min = currents.iter().copied().fold(f64::MAX, f64::min);
+        let imbalanced = max > 0.0 && (max - min) / max > self.config.imbalance_threshold;
+
+        if imbalanced {
+            self.rec.log(
+                entity_path(root, "alerts/phase_imbalance"),
+                &TextLog::new(format!(
+                    "Phase imbalance of {:.0} %: L1 {} A, L2 {} A, L3 {} A",
+                    (max - min) / max * 100.0,
+                    currents[0],
+                    currents[1],
+                    currents[2]
+                ))
+                .with_level(TextLogLevel::WARN),
+            )?;
+        }
+        self.rec.log(
+            entity_path(root, "diagnostics/phase_imbalanced"),
+            &Scalars::single(if imbalanced { 1.0 } else { 0.0 }),
+        )?;
+
+        Ok(())
+    }
+
+    /// Logs the missing acknowledgment next to the received ones and the call it belongs to.
+    fn plot_call_timeout(
+        &self,
+        root: &str,
+        action: &str,
+        unique_id: &str,
+        timeout_s: f64,
+    ) -> anyhow::Result<()> {
+        let station = self.layout.station(root);
+        self.rec.log(
+            entity_path(&station, &format!("ocpp/ack/{}", action)),
+            &Scalars::single(0.0),
+        )?;
+        self.rec.log(
+            entity_path(&station, &format!("ocpp/ack_timeout/{}", action)),
+            &TextLog::new(format!(
+                "No answer to {} call {} within {} s",
+                action, unique_id, timeout_s
+            ))
+            .with_level(TextLogLevel::WARN),
+        )?;
+
+        Ok(())
+    }
+
+    /// Logs the error and the number of errors of the action so far.
+    fn plot_call_error(
+        &mut self,
+        root: &str,
+        action: &str,
+        error_code: &str,
+        error_description: &str,
+    ) -> anyhow::Result<()> {
+        let station = self.layout.station(root);
+        self.rec.log(
+            entity_path(&station, &format!("ocpp/errors/{}/{}", action, error_code)),
+            &TextLog::new(format!("{}: {}", error_code, error_description))
+                .with_level(TextLogLevel::ERROR),
+        )?;
+
+        let entity_path = entity_path(&station, &format!("ocpp/error_count/{}", action));
+        let count = self.call_errors.entry(entity_path.clone()).or_default();
+        *count += 1;
+        let count = *count as f64;
+        self.rec.log(entity_path, &Scalars::single(count))?;
+
+        Ok(())
+    }
+
+    /// Logs the new value of the key and counts the change, whether or not it was accepted, since
+    /// a rejected change hints at a misconfigured central system as well.
+    fn plot_configuration_change(
+        &self,
+        root: &str,
+        key: &str,
+        value: &str,
+        status: &ConfigurationStatus,
+    ) -> anyhow::Result<()> {
+        let station = self.layout.station(root);
+        self.rec.log(
+            entity_path(&station, &format!("config/{}", key)),
+            &TextLog::new(format!("{} = {} ({})", key, value, ocpp_name(status))).with_level(
+                match status {
+                    ConfigurationStatus::Accepted | ConfigurationStatus::RebootRequired => {
+                        TextLogLevel::INFO
+                    }
+                    ConfigurationStatus::Rejected | ConfigurationStatus::NotSupported => {
+                        TextLogLevel::WARN
+                    }
+                },
+            ),
+        )?;
+        self.rec.log(
+            entity_path(&station, "config/changes"),
+            &Scalars::single(1.0),
+        )?;
+
+        Ok(())
+    }
+
+    /// Logs the answered Reset call, with a marker colored by its type for the accepted ones.
+    fn plot_reset(
+        &mut self,
+        root: &str,
+        kind: &ResetRequestStatus,
+        status: &ResetResponseStatus,
+    ) -> anyhow::Result<()> {
+        let (path, color) = match kind {
+            ResetRequestStatus::Hard => ("ocpp/commands/reset/hard", [255, 0, 0]),
+            ResetRequestStatus::Soft => ("ocpp/commands/reset/soft", [255, 220, 0]),
+        };
+        self.rec.log(
+            entity_path(&self.layout.station(root), "ocpp/commands/reset"),
+            &TextLog::new(format!(
+                "{} reset {}",
+                ocpp_name(kind),
+                ocpp_name(status).to_lowercase()
+            ))
+            .with_level(match status {
+                ResetResponseStatus::Accepted => TextLogLevel::INFO,
+                ResetResponseStatus::Rejected => TextLogLevel::WARN,
+            })
+            .with_color(color),
+        )?;
+        if *status == ResetResponseStatus::Accepted {
+            self.plot_marker(root, path, color, 1.0)?;
+        }
+
+        Ok(())
+    }
+
+    fn plot_remote_command(
+        &self,
+        root: &str,
+        command: &str,
+        text: String,
+        status: &RemoteStartStopStatus,
+    ) -> anyhow::Result<()> {
+        self.rec.log(
+            entity_path(
+                &self.layout.station(root),
+                &format!("ocpp/commands/{}", command),
+            ),
+            &TextLog::new(format!("{}: {}", text, ocpp_name(status))).with_level(match status {
+                RemoteStartStopStatus::Accepted => TextLogLevel::INFO,
+                RemoteStartStopStatus::Rejected => TextLogLevel::WARN,
+            }),
+        )?;
+
+        Ok(())
+    }
+
+    /// Logs the time from the answer to a remote command until the station acted on it, next to
+    /// the latencies of the calls.
+    fn plot_command_latency(
+        &self,
+        root: &str,
+        command: &str,
+        latency_s: f64,
+    ) -> anyhow::Result<()> {
+        self.rec.log(
+            entity_path(
+                &self.layout.station(root),
+                &format!("latency/{}_to_transaction_ms", command),
+            ),
+            &Scalars::single(latency_s * 1000.0),
+        )?;
+
+        Ok(())
+    }
+
+    /// Logs the result of the unlock attempt and the number of attempts on the connector so far,
+    /// as repeated attempts point at a stuck cable.
+    fn plot_unlock_connector(
+        &mut self,
+        root: &str,
+        connector_id: u32,
+        status: &UnlockStatus,
+    ) -> anyhow::Result<()> {
+        let connector_path = entity_path(
+            &self.layout.station(root),
+            &format!("connector/{}", connector_id),
+        );
+        self.rec.log(
+            format!("{}/unlock", connector_path),
+            &TextLog::new(ocpp_name(status)).with_level(match status {
+                UnlockStatus::Unlocked => TextLogLevel::INFO,
+                UnlockStatus::UnlockFailed => TextLogLevel::ERROR,
+                UnlockStatus::NotSupported => TextLogLevel::WARN,
+            }),
+        )?;
+
+        let entity_path = format!("{}/unlock_attempts", connector_path);
+        let count = self.unlock_attempts.entry(entity_path.clone()).or_default();
+        *count += 1;
+        let count = *count as f64;
+        self.rec.log(entity_path, &Scalars::single(count))?;
+
+        Ok(())
+    }
+
+    /// Logs the answered TriggerMessage call below the requested message, so that a message sent
+    /// outside of its schedule can be traced back to it.
+    fn plot_trigger_message(
+        &self,
+        root: &str,
+        requested_message: &MessageTrigger,
+        connector_id: Option<u32>,
+        status: &TriggerMessageStatus,
+    ) -> anyhow::Result<()> {
+        let requested_message = ocpp_name(requested_message);
+        let connector = connector_id
+            .map(|connector_id| format!(" on connector {}", connector_id))
+            .unwrap_or_default();
+
+        self.rec.log(
+            entity_path(
+                &self.layout.station(root),
+                &format!("ocpp/commands/trigger/{}", requested_message),
+            ),
+            &TextLog::new(format!(
+                "Trigger of {}{}: {}",
+                requested_message,
+                connector,
+                ocpp_name(status)
+            ))
+            .with_level(match status {
+                TriggerMessageStatus::Accepted => TextLogLevel::INFO,
+                TriggerMessageStatus::Rejected | TriggerMessageStatus::NotImplemented => {
+                    TextLogLevel::WARN
+                }
+            }),
+        )?;
+
+        Ok(())
+    }
+
+    /// Logs the fields of the vendor's schema, the others are unknown and skipped.
+    fn plot_data_transfer(
+        &mut self,
+        root: &str,
+        vendor_id: &str,
+        fields: &[(String, f64)],
+    ) -> anyhow::Result<()> {
+        let Some(units) = self.config.data_transfer_schema.get(vendor_id) else {
+            return Ok(());
+        };
+        let fields = fields
+            .iter()
+            .filter_map(|(field, value)| Some((field, units.get(field)?.clone(), *value)))
+            .collect::<Vec<_>>();
+
+        for (field, unit, value) in fields {
+            let entity_path = entity_path(
+                &self.layout.station(root),
+                &format!("datatransfer/{}/{}", vendor_id, field),
+            );
+            self.style_series(&entity_path, |_| {
+                SeriesLines::new()
+                    .with_names([format!("{} [{}]", field, unit)])
+                    .with_widths([SERIES_WIDTH])
+            })?;
+            self.rec.log(entity_path, &Scalars::single(value))?;
+        }
+
+        Ok(())
+    }
+
+    /// Logs the peak power of a transaction once it stopped.
+    fn finish_session(&mut self, station_id: &str, transaction_id: &str) -> anyhow::Result<()> {
+        let key = (station_id.to_owned(), transaction_id.to_owned());
+        let Some(peak) = self.peak_powers.remove(&key) else {
+            return Ok(());
+        };
+
+        self.rec
+            .log_static(peak.path.as_str(), &Scalars::single(peak.power_w))?;
+        self.session_peaks.push((key.0, key.1, peak.power_w));
+
+        Ok(())
+    }
+
+    /// Logs the peak power of the transactions that didn't stop within the traces.
+    pub fn finish_sessions(&mut self) -> anyhow::Result<()> {
+        let open_sessions = self
+            .peak_powers
+            .keys()
+            .cloned()
+            .sorted()
+            .collect::<Vec<_>>();
+        for (station_id, transaction_id) in open_sessions {
+            self.finish_session(&station_id, &transaction_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Counts the last status of every connector up to the last entry and logs a summary of the
+    /// time spent per status.
+    pub fn finish_connectors(&mut self) -> anyhow::Result<()> {
+        let timestamp_s = self.timestamp_s;
+        for (connector_path, availability) in self
+            .connector_availabilities
+            .iter_mut()
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        {
+            availability.transition(availability.status.clone(), timestamp_s);
+            if availability.total_s <= 0.0 {
+                continue;
+            }
+
+            self.rec.log(
+                format!("{}/availability_summary", connector_path),
+                &TextLog::new(format!(
+                    "Uptime {:.1} %, charging {:.1} %, faulted {:.1} % of {:.0} s",
+                    availability.percentage(availability.uptime_s),
+                    availability.percentage(availability.charging_s),
+                    availability.percentage(availability.fault_s),
+                    availability.total_s
+                ))
+                .with_level(TextLogLevel::INFO),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the peak power of the transactions and the number of duplicate values to stderr.
+    pub fn print_summary(&self) {
+        eprintln!("Duplicate values skipped:     {}", self.duplicates);
+        for (station_id, transaction_id, power_w) in &self.session_peaks {
+            if station_id.is_empty() {
+                eprintln!(
+                    "Peak power of transaction {}: {:.0} W",
+                    transaction_id, power_w
+                );
+            } else {
+                eprintln!(
+                    "Peak power of transaction {} of {}: {:.0} W",
+                    transaction_id, station_id, power_w
+                );
+            }
+        }
+    }
+
+    /// Logs PF = P / (V × I) for one phase, clamped to the physically valid range.
+    fn plot_derived_power_factor(
+        &mut self,
+        root: &str,
+        phase: &Phase,
+        power_factor: f64,
+    ) -> anyhow::Result<()> {
+        if !(-1.0..=1.0).contains(&power_factor) {
+            re_log::warn!(
+                "Derived power factor {power_factor} on {phase:?} is outside of [-1, 1], clamping"
+            );
+        }
+
+        let entity_path = entity_path(
+            root,
+            &format!("power_factor/{}", phase_to_path_segment(phase)),
+        );
+        self.style_series(&entity_path, |theme| {
+            series_style(theme, &Measurand::PowerFactor, &Some(phase.clone()))
+                .with_names([format!("Derived power factor {}", ocpp_name(phase))])
+        })?;
+        self.log_sample(&entity_path, power_factor.clamp(-1.0, 1.0))?;
+
+        Ok(())
+    }
+
+    /// Logs the displacement power factor cos(atan2(Q, P)) of the summed phases and flags it below
+    /// the minimum.
+    fn plot_total_power_factor(&mut self, root: &str, power_factor: f64) -> anyhow::Result<()> {
+        let series_path = entity_path(root, "power_factor/total");
+        self.style_series(&series_path, |theme| {
+            series_style(theme, &Measurand::PowerFactor, &None).with_names(["Total power factor"])
+        })?;
+        self.log_sample(&series_path, power_factor)?;
+
+        if power_factor < self.config.power_factor_min_alert {
+            self.rec.log(
+                entity_path(root, "alerts/low_power_factor"),
+                &TextLog::new(format!(
+                    "Total power factor {:.3} below {}",
+                    power_factor, self.config.power_factor_min_alert
+                ))
+                .with_level(TextLogLevel::WARN),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn plot_transaction_event(&self, root: &str, text: String) -> anyhow::Result<()> {
+        self.rec.log(
+            entity_path(&self.layout.station(root), "transaction/events"),
+            &TextLog::new(text).with_level(TextLogLevel::INFO),
+        )?;
+
+        Ok(())
+    }
+
+    /// Logs `value` at `path` below the station as a point without a line, in `color`.
+    fn plot_marker(
+        &mut self,
+        root: &str,
+        path: &str,
+        color: [u8; 3],
+        value: f64,
+    ) -> anyhow::Result<()> {
+        let entity_path = entity_path(&self.layout.station(root), path);
+
+        if self.styled_entities.insert(entity_path.clone()) {
+            self.rec.log_static(
+                entity_path.as_str(),
+                &SeriesPoints::new()
+                    .with_colors([color])
+                    .with_markers([MarkerShape::Diamond])
+                    .with_marker_sizes([6.0]),
+            )?;
+        }
+
+        self.rec.log(entity_path, &Scalars::single(value))?;
+
+        Ok(())
+    }
+
+    /// Logs the connector status both as an integer series, so state changes line up with the
+    /// meter values, and as a text log entry.
+    fn plot_status_notification(
+        &mut self,
+        root: &str,
+        timestamp_s: f64,
+        request: &StatusNotificationRequest,
+    ) -> anyhow::Result<()> {
+        let connector_path = entity_path(
+            &self.layout.station(root),
+            &format!("connector/{}", request.connector_id),
+        );
+        let level = match request.status {
+            ChargePointStatus::Faulted | ChargePointStatus::Unavailable => TextLogLevel::WARN,
+            _ => TextLogLevel::INFO,
+        };
+
+        self.rec.log(
+            format!("{}/status", connector_path),
+            &Scalars::single(connector_status_index(&request.status)),
+        )?;
+        self.rec.log(
+            format!("{}/status", connector_path),
+            &TextLog::new(format!("{:?}", request.status)).with_level(level),
+        )?;
+
+        if request.error_code != ChargePointErrorCode::NoError {
+            let mut text = format!("{:?}", request.error_code);
+            if let Some(vendor_error_code) = &request.vendor_error_code {
+                text.push_str(&format!(" (vendor error code: {})", vendor_error_code));
+            }
+            if let Some(info) = &request.info {
+                text.push_str(&format!(": {}", info));
+            }
+
+            self.rec.log(
+                format!("{}/error_code", connector_path),
+                &TextLog::new(text).with_level(TextLogLevel::ERROR),
+            )?;
+        }
+
+        self.plot_connector_availability(connector_path, timestamp_s, &request.status)?;
+
+        Ok(())
+    }
+
+    /// Logs the fraction of the time since the first StatusNotification of the connector that it
+    /// was neither Faulted nor Unavailable.
+    fn plot_connector_availability(
+        &mut self,
+        connector_path: String,
+        timestamp_s: f64,
+        status: &ChargePointStatus,
+    ) -> anyhow::Result<()> {
+        let availability = match self.connector_availabilities.entry(connector_path.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(ConnectorAvailability::new(status.clone(), timestamp_s));
+                return Ok(());
+            }
+        };
+        availability.transition(status.clone(), timestamp_s);
+
+        if availability.total_s > 0.0 {
+            self.rec.log(
+                format!("{}/availability_fraction", connector_path),
+                &Scalars::single(availability.uptime_s / availability.total_s),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn plot_heartbeat(&mut self, root: &str, timestamp_s: f64) -> anyhow::Result<()> {
+        let heartbeat_path = entity_path(&self.layout.station(root), "heartbeat");
+
+        self.rec.log(
+            format!("{}/received", heartbeat_path),
+            &Scalars::single(1.0),
+        )?;
+
+        if let Some(last_heartbeat_s) = self.last_heartbeats.insert(root.to_owned(), timestamp_s) {
+            let interval_s = timestamp_s - last_heartbeat_s;
+            if interval_s > self.config.heartbeat_alarm_threshold_s {
+                re_log::warn!(
+                    "No heartbeat from {} for {interval_s} s",
+                    if root.is_empty() { "the station" } else { root }
+                );
+            }
+
+            self.rec.log(
+                format!("{}/interval_s", heartbeat_path),
+                &Scalars::single(interval_s),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Logs the station metadata at the time of the boot instead of statically, so a later boot
+    /// after a firmware upgrade doesn't hide the earlier firmware version.
+    fn plot_boot_notification(
+        &mut self,
+        root: &str,
+        request: &BootNotificationRequest,
+    ) -> anyhow::Result<()> {
+        let optional = |value: &Option<String>| value.as_deref().unwrap_or("-").to_owned();
+        let station_path = self.layout.station(root);
+
+        // Labels the station entity with its model in the viewer, overwritten by every boot.
+        self.rec.log_static(
+            station_path.as_str(),
+            &AnnotationContext::new([(
+                0,
+                format!(
+                    "{} {}",
+                    request.charge_point_vendor, request.charge_point_model
+                )
+                .as_str(),
+            )]),
+        )?;
+
+        self.rec.log(
+            entity_path(&station_path, "info"),
+            &TextDocument::from_markdown(format!(
+                "* Vendor: {}\n* Model: {}\n* Serial number: {}\n* Firmware version: {}\n",
+                request.charge_point_vendor,
+                request.charge_point_model,
+                optional(&request.charge_point_serial_number),
+                optional(&request.firmware_version),
+            )),
+        )?;
+
+        let previous_version = self
+            .firmware_versions
+            .insert(root.to_owned(), request.firmware_version.clone())
+            .flatten();
+        if self.installed_firmware.remove(root) {
+            self.plot_firmware_upgrade(root, previous_version, &request.firmware_version)?;
+        }
+
+        Ok(())
+    }
+
+    /// Logs whether the first boot after an installed firmware reports a new firmware version.
+    fn plot_firmware_upgrade(
+        &self,
+        root: &str,
+        previous_version: Option<String>,
+        version: &Option<String>,
+    ) -> anyhow::Result<()> {
+        let previous_version = previous_version.unwrap_or("-".to_owned());
+        let version = version.as_deref().unwrap_or("-");
+        let text_log = if previous_version == version {
+            TextLog::new(format!(
+                "Firmware still at version {} after the installation",
+                version
+            ))
+            .with_level(TextLogLevel::WARN)
+        } else {
+            TextLog::new(format!(
+                "Firmware upgrade from version {} to {} completed",
+                previous_version, version
+            ))
+            .with_level(TextLogLevel::INFO)
+        };
+        self.rec.log(
+            entity_path(&self.layout.station(root), "firmware/status"),
+            &text_log,
+        )?;
+
+        Ok(())
+    }
+
+    /// Logs the status as text and as a code that orders the states of an update, with the
+    /// failures below the idle state.
+    fn plot_firmware_status(&mut self, root: &str, status: &FirmwareStatus) -> anyhow::Result<()> {
+        let station = self.layout.station(root);
+        let (code, level) = match status {
+            FirmwareStatus::Idle => (0.0, TextLogLevel::DEBUG),
+            FirmwareStatus::Downloading => (0.5, TextLogLevel::INFO),
+            FirmwareStatus::Downloaded => (1.0, TextLogLevel::INFO),
+            FirmwareStatus::Installing => (2.0, TextLogLevel::INFO),
+            FirmwareStatus::Installed => (3.0, TextLogLevel::INFO),
+            FirmwareStatus::DownloadFailed | FirmwareStatus::InstallationFailed => {
+                (-1.0, TextLogLevel::ERROR)
+            }
+        };
+        if *status == FirmwareStatus::Installed {
+            self.installed_firmware.insert(root.to_owned());
+        }
+
+        self.rec.log(
+            entity_path(&station, "firmware/status"),
+            &TextLog::new(ocpp_name(status)).with_level(level),
+        )?;
+        self.rec.log(
+            entity_path(&station, "firmware/status_code"),
+            &Scalars::single(code),
+        )?;
+
+        Ok(())
+    }
+
+    /// Logs the status of a diagnostics upload like [`TracePlotter::plot_firmware_status`].
+    fn plot_diagnostics_status(
+        &self,
+        root: &str,
+        status: &DiagnosticsStatus,
+    ) -> anyhow::Result<()> {
+        let station = self.layout.station(root);
+        let (code, level) = match status {
+            DiagnosticsStatus::Idle => (0.0, TextLogLevel::DEBUG),
+            DiagnosticsStatus::Uploading => (1.0, TextLogLevel::INFO),
+            DiagnosticsStatus::Uploaded => (2.0, TextLogLevel::INFO),
+            DiagnosticsStatus::UploadFailed => (-1.0, TextLogLevel::ERROR),
+        };
+
+        self.rec.log(
+            entity_path(&station, "diagnostics/status"),
+            &TextLog::new(ocpp_name(status)).with_level(level),
+        )?;
+        self.rec.log(
+            entity_path(&station, "diagnostics/status_code"),
+            &Scalars::single(code),
+        )?;
+
+        Ok(())
+    }
+
+    pub fn plot_trace_file_entry(
+        &mut self,
+        trace_file_entry: &TraceFileEntry,
+    ) -> anyhow::Result<()> {
+        let timestamp_s = trace_file_entry.timestamp.timestamp() as f64
+            + trace_file_entry.timestamp.timestamp_subsec_nanos() as f64 / 1e9;
+        self.set_time(timestamp_s);
+        self.layout = self
+            .config
+            .layout
+            .on_date(trace_file_entry.timestamp.date_naive());
+        let root = trace_file_entry.station_id.as_deref().unwrap_or_default();
+
+        match &trace_file_entry.message {
+            OcppMessage::MeterValues(meter_values) => self.plot_meter_values(
+                root,
+                &MeterRoot::new(&self.layout, root, meter_values).path,
+                timestamp_s,
+                meter_values,
+            )?,
+            OcppMessage::StartTransaction(request) => {
+                self.plot_transaction_event(
+                    root,
+                    format!(
+                        "StartTransaction on connector {} for idTag {} (meterStart: {} Wh)",
+                        request.connector_id, request.id_tag, request.meter_start
+                    ),
+                )?;
+                self.plot_marker(
+                    root,
+                    "transaction/meter_start",
+                    [0, 200, 0],
+                    request.meter_start as f64,
+                )?;
+                if let Some(command_s) = self
+                    .remote_starts
+                    .remove(&(root.to_owned(), request.id_tag.clone()))
+                {
+                    self.plot_command_latency(root, "remote_start", timestamp_s - command_s)?;
+                }
+            }
+            OcppMessage::StartTransactionResponse { response, .. } => {
+                self.plot_transaction_event(
+                    root,
+                    format!(
+                        "Transaction {} started (idTag status: {:?})",
+                        response.transaction_id, response.id_tag_info.status
+                    ),
+                )?;
+            }
+            OcppMessage::StopTransaction(request) => {
+                self.plot_transaction_event(
+                    root,
+                    format!(
+                        "StopTransaction for transaction {} (meterStop: {} Wh, reason: {:?})",
+                        request.transaction_id,
+                        request.meter_stop,
+                        // OCPP 1.6 defines Local as the default reason.
+                        request.reason.clone().unwrap_or(Reason::Local)
+                    ),
+                )?;
+                self.plot_marker(
+                    root,
+                    "transaction/meter_stop",
+                    [255, 0, 0],
+                    request.meter_stop as f64,
+                )?;
+                self.finish_session(root, &request.transaction_id.to_string())?;
+                if let Some(command_s) = self
+                    .remote_stops
+                    .remove(&(root.to_owned(), request.transaction_id))
+                {
+                    self.plot_command_latency(root, "remote_stop", timestamp_s - command_s)?;
+                }
+            }
+            OcppMessage::StopTransactionResponse(response) => {
+                self.plot_transaction_event(
+                    root,
+                    match &response.id_tag_info {
+                        Some(id_tag_info) => {
+                            format!(
+                                "Transaction stopped (idTag status: {:?})",
+                                id_tag_info.status
+                            )
+                        }
+                        None => "Transaction stopped".to_owned(),
+                    },
+                )?;
+            }
+            OcppMessage::StatusNotification(request) => {
+                self.plot_status_notification(root, timestamp_s, request)?
+            }
+            OcppMessage::Heartbeat => self.plot_heartbeat(root, timestamp_s)?,
+            OcppMessage::BootNotification(request) => self.plot_boot_notification(root, request)?,
+            OcppMessage::FirmwareStatusNotification(request) => {
+                self.plot_firmware_status(root, &request.status)?
+            }
+            OcppMessage::DiagnosticsStatusNotification(request) => {
+                self.plot_diagnostics_status(root, &request.status)?
+            }
+            OcppMessage::DataTransfer { vendor_id, fields } => {
+                self.plot_data_transfer(root, vendor_id, fields)?
+            }
+            OcppMessage::ConfigurationChange { key, value, status } => {
+                self.plot_configuration_change(root, key, value, status)?
+            }
+            OcppMessage::Reset { kind, status } => self.plot_reset(root, kind, status)?,
+            OcppMessage::RemoteStartTransaction {
+                id_tag,
+                connector_id,
+                charging_profile_id,
+                status,
+            } => {
+                let connector = connector_id
+                    .map(|connector_id| format!(" on connector {}", connector_id))
+                    .unwrap_or_default();
+                let charging_profile = charging_profile_id
+                    .map(|charging_profile_id| {
+                        format!(" with charging profile {}", charging_profile_id)
+                    })
+                    .unwrap_or_default();
+                self.plot_remote_command(
+                    root,
+                    "remote_start",
+                    format!(
+                        "RemoteStartTransaction for idTag {}{}{}",
+                        id_tag, connector, charging_profile
+                    ),
+                    status,
+                )?;
+                if *status == RemoteStartStopStatus::Accepted {
+                    self.remote_starts
+                        .insert((root.to_owned(), id_tag.clone()), timestamp_s);
+                }
+            }
+            OcppMessage::RemoteStopTransaction {
+                transaction_id,
+                status,
+            } => {
+                self.plot_remote_command(
+                    root,
+                    "remote_stop",
+                    format!("RemoteStopTransaction for transaction {}", transaction_id),
+                    status,
+                )?;
+                if *status == RemoteStartStopStatus::Accepted {
+                    self.remote_stops
+                        .insert((root.to_owned(), *transaction_id), timestamp_s);
+                }
+            }
+            OcppMessage::UnlockConnector {
+                connector_id,
+                status,
+            } => self.plot_unlock_connector(root, *connector_id, status)?,
+            OcppMessage::TriggerMessage {
+                requested_message,
+                connector_id,
+                status,
+            } => self.plot_trigger_message(root, requested_message, *connector_id, status)?,
+            OcppMessage::Configuration(keys) => {
+                for key_value in keys {
+                    self.rec.log(
+                        entity_path(
+                            &self.layout.station(root),
+                            &format!("config/{}", key_value.key),
+                        ),
+                        &TextLog::new(format!(
+                            "{} = {}{}",
+                            key_value.key,
+                            key_value.value.as_deref().unwrap_or("-"),
+                            if key_value.readonly {
+                                " (read-only)"
+                            } else {
+                                ""
+                            }
+                        ))
+                        .with_level(TextLogLevel::DEBUG),
+                    )?;
+                }
+            }
+            OcppMessage::CallLatency { action, latency_ms } => self.rec.log(
+                entity_path(
+                    &self.layout.station(root),
+                    &format!("latency/{}_ms", action),
+                ),
+                &Scalars::single(*latency_ms),
+            )?,
+            OcppMessage::CallError {
+                action,
+                error_code,
+                error_description,
+            } => self.plot_call_error(root, action, error_code, error_description)?,
+            OcppMessage::CallAck { action } => self.rec.log(
+                entity_path(&self.layout.station(root), &format!("ocpp/ack/{}", action)),
+                &Scalars::single(1.0),
+            )?,
+            OcppMessage::CallTimeout {
+                action,
+                unique_id,
+                timeout_s,
+            } => self.plot_call_timeout(root, action, unique_id, *timeout_s)?,
+            OcppMessage::SchemaViolation { action, violations } => self.rec.log(
+                entity_path(
+                    &self.layout.station(root),
+                    &format!("validation/{}", action),
+                ),
+                &TextLog::new(violations.join("\n")).with_level(TextLogLevel::WARN),
+            )?,
+            OcppMessage::Raw { action, json } => self.rec.log(
+                entity_path(&self.layout.station(root), &format!("ocpp/raw/{}", action)),
+                &TextLog::new(json.as_str()).with_level(TextLogLevel::DEBUG),
+            )?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Position of `status` in the OCPP 1.6 ChargePointStatus enumeration, for plotting.
+pub fn connector_status_index(status: &ChargePointStatus) -> f64 {
+    match status {
+        ChargePointStatus::Available => 0.0,
+        ChargePointStatus::Preparing => 1.0,
+        ChargePointStatus::Charging => 2.0,
+        ChargePointStatus::SuspendedEVSE => 3.0,
+        ChargePointStatus::SuspendedEV => 4.0,
+        ChargePointStatus::Finishing => 5.0,
+        ChargePointStatus::Reserved => 6.0,
+        ChargePointStatus::Unavailable => 7.0,
+        ChargePointStatus::Faulted => 8.0,
+    }
+}
+
+pub fn plot_log_file_entry(
+    rec: &RecordingStream,
+    root: &str,
+    log_file_entry: &LogFileEntry,
+) -> anyhow::Result<()> {
+    rec.set_timestamp_secs_since_epoch("time", log_file_entry.timestamp);
+
+    plot_pv_production(rec, root, log_file_entry.pv_overproduction)?;
+    plot_battery_load(rec, root, log_file_entry.battery_load)?;
+    plot_ev_import(rec, root, log_file_entry.ev_import)?;
+    plot_load_overall(rec, root, log_file_entry.load_overall)?;
+    plot_overproduction(rec, root, log_file_entry.overproduction)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rerun::log::{Chunk, LogMsg};
+
+    fn plotter(rec: &RecordingStream) -> TracePlotter {
+        TracePlotter::new(
+            rec,
+            PlotConfig {
+                heartbeat_alarm_threshold_s: 300.0,
+                rolling_avg_window_s: None,
+                sample_rate: None,
+                derivative: false,
+                imbalance_threshold: 0.2,
+                nominal_voltage_v: 230.0,
+                voltage_tolerance_pct: 10.0,
+                temperature_max_c: 80.0,
+                nominal_frequency_hz: 50.0,
+                frequency_tolerance_hz: 0.5,
+                rpm_stall_threshold: 500.0,
+                power_factor_min_alert: 0.9,
+                data_transfer_schema: DataTransferSchema::new(),
+                layout: PathLayout::default(),
+                theme: ThemeMap::load("default").unwrap(),
+            },
+        )
+    }
+
+    fn logged_rows(messages: &[LogMsg], entity_path: &str) -> usize {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                LogMsg::ArrowMsg(_, arrow_msg) => Chunk::from_arrow_msg(arrow_msg).ok(),
+                _ => None,
+            })
+            .filter(|chunk| chunk.entity_path().to_string() == entity_path)
+            .map(|chunk| chunk.num_rows())
+            .sum()
+    }
+
+    #[test]
+    fn retransmitted_over_voltage_alerts_once() {
+        let (rec, storage) = rerun::RecordingStreamBuilder::new("test").memory().unwrap();
+        let mut plotter = plotter(&rec);
+        let meter_values = MeterValuesBatch {
+            connector_id: Some(1),
+            transaction_id: None,
+            readings: vec![SampledReading {
+                measurand: Measurand::Voltage,
+                phase: Some(Phase::L1),
+                unit: None,
+                value: 260.0,
+            }],
+            invalid_readings: Vec::new(),
+        };
+
+        plotter.set_time(1_700_000_000.0);
+        for _ in 0..2 {
+            plotter
+                .plot_meter_values("CP1", "CP1", 1_700_000_000.0, &meter_values)
+                .unwrap();
+        }
+        rec.flush_blocking().unwrap();
+
+        let messages = storage.take();
+        assert_eq!(logged_rows(&messages, "/CP1/alerts/voltage/L1"), 1);
+        assert_eq!(
+            logged_rows(&messages, "/CP1/diagnostics/voltage_out_of_range/L1"),
+            1
+        );
+    }
+}
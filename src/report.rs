@@ -0,0 +1,277 @@
+//! Summary of the charging sessions found in the traces, one row per transaction.
+
+use std::collections::HashMap;
+use std::io;
+
+use chrono::{DateTime, FixedOffset};
+use chrono_tz::Tz;
+use comfy_table::Table;
+use serde::Serialize;
+
+use log_viewer::ocpp::{OcppMessage, TraceFileEntry, total_power_active_import};
+
+use crate::plot::EnergyAccumulator;
+
+/// Charging session of one transaction, built up from its messages.
+struct Session {
+    station_id: String,
+    connector_id: Option<u32>,
+    transaction_id: String,
+    start_time: Option<DateTime<FixedOffset>>,
+    /// Time of the StopTransaction, or of the last meter values of a transaction that didn't stop
+    /// within the traces.
+    end_time: Option<DateTime<FixedOffset>>,
+    stopped: bool,
+    meter_start_wh: Option<i32>,
+    meter_stop_wh: Option<i32>,
+    peak_power_w: Option<f64>,
+    integrated_energy: Option<EnergyAccumulator>,
+}
+
+impl Session {
+    fn new(station_id: &str, transaction_id: &str) -> Self {
+        Self {
+            station_id: station_id.to_owned(),
+            connector_id: None,
+            transaction_id: transaction_id.to_owned(),
+            start_time: None,
+            end_time: None,
+            stopped: false,
+            meter_start_wh: None,
+            meter_stop_wh: None,
+            peak_power_w: None,
+            integrated_energy: None,
+        }
+    }
+
+    /// From the meter readings of the StartTransaction and StopTransaction if both are known, or
+    /// from the integrated power otherwise.
+    fn energy_kwh(&self) -> Option<f64> {
+        match (self.meter_start_wh, self.meter_stop_wh) {
+            (Some(meter_start_wh), Some(meter_stop_wh)) => {
+                // In f64, the difference of two i32 can overflow them.
+                Some((f64::from(meter_stop_wh) - f64::from(meter_start_wh)) / 1000.0)
+            }
+            _ => self
+                .integrated_energy
+                .as_ref()
+                .map(|accumulator| accumulator.energy_wh / 1000.0),
+        }
+    }
+
+    fn row(&self, timezone: Tz) -> SessionRow {
+        SessionRow {
+            station_id: self.station_id.clone(),
+            connector_id: self.connector_id,
+            transaction_id: self.transaction_id.clone(),
+            start_time: self
+                .start_time
+                .map(|start_time| start_time.with_timezone(&timezone).to_rfc3339()),
+            end_time: self
+                .end_time
+                .map(|end_time| end_time.with_timezone(&timezone).to_rfc3339()),
+            duration_min: self
+                .start_time
+                .zip(self.end_time)
+                .map(|(start_time, end_time)| (end_time - start_time).as_seconds_f64() / 60.0),
+            peak_power_kw: self.peak_power_w.map(|power_w| power_w / 1000.0),
+            energy_kwh: self.energy_kwh(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SessionRow {
+    station_id: String,
+    connector_id: Option<u32>,
+    transaction_id: String,
+    start_time: Option<String>,
+    end_time: Option<String>,
+    duration_min: Option<f64>,
+    peak_power_kw: Option<f64>,
+    energy_kwh: Option<f64>,
+}
+
+pub const COLUMNS: [&str; 8] = [
+    "station_id",
+    "connector_id",
+    "transaction_id",
+    "start_time",
+    "end_time",
+    "duration_min",
+    "peak_power_kw",
+    "energy_kwh",
+];
+
+impl SessionRow {
+    fn cells(&self) -> [String; 8] {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        let decimal = |value: Option<f64>| optional(value.map(|value| format!("{:.2}", value)));
+
+        [
+            self.station_id.clone(),
+            optional(
+                self.connector_id
+                    .map(|connector_id| connector_id.to_string()),
+            ),
+            self.transaction_id.clone(),
+            optional(self.start_time.clone()),
+            optional(self.end_time.clone()),
+            decimal(self.duration_min),
+            decimal(self.peak_power_kw),
+            decimal(self.energy_kwh),
+        ]
+    }
+}
+
+pub enum ReportFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+/// Collects the sessions of the plotted entries, in the order they started.
+pub struct SessionReport {
+    format: ReportFormat,
+    /// Of the start and end times.
+    timezone: Tz,
+    sessions: Vec<Session>,
+    /// Index into `sessions`, keyed by station and transaction ID.
+    indices: HashMap<(String, String), usize>,
+    /// Time of the last StartTransaction call, keyed by station and connector.
+    start_times: HashMap<(String, u32), DateTime<FixedOffset>>,
+}
+
+impl SessionReport {
+    pub fn new(format: ReportFormat, timezone: Tz) -> Self {
+        Self {
+            format,
+            timezone,
+            sessions: Vec::new(),
+            indices: HashMap::new(),
+            start_times: HashMap::new(),
+        }
+    }
+
+    fn session(&mut self, station_id: &str, transaction_id: &str) -> &mut Session {
+        let index = *self
+            .indices
+            .entry((station_id.to_owned(), transaction_id.to_owned()))
+            .or_insert_with(|| {
+                self.sessions.push(Session::new(station_id, transaction_id));
+                self.sessions.len() - 1
+            });
+
+        &mut self.sessions[index]
+    }
+
+    pub fn add(&mut self, trace_file_entry: &TraceFileEntry) {
+        let station_id = trace_file_entry.station_id.as_deref().unwrap_or_default();
+        let timestamp = trace_file_entry.timestamp;
+
+        match &trace_file_entry.message {
+            OcppMessage::StartTransaction(request) => {
+                self.start_times
+                    .insert((station_id.to_owned(), request.connector_id), timestamp);
+            }
+            // Paired with its call by uniqueId, so a start on another connector or one whose
+            // result was lost can't take its place.
+            OcppMessage::StartTransactionResponse { request, response } => {
+                let start_time = self
+                    .start_times
+                    .remove(&(station_id.to_owned(), request.connector_id))
+                    .unwrap_or(timestamp);
+
+                let session = self.session(station_id, &response.transaction_id.to_string());
+                session.connector_id = Some(request.connector_id);
+                session.start_time = Some(start_time);
+                session.meter_start_wh = Some(request.meter_start);
+            }
+            OcppMessage::MeterValues(meter_values) => {
+                let Some(transaction_id) = &meter_values.transaction_id else {
+                    return;
+                };
+
+                let session = self.session(station_id, transaction_id);
+                session.connector_id = session.connector_id.or(meter_values.connector_id);
+                session.start_time.get_or_insert(timestamp);
+                if !session.stopped {
+                    session.end_time = Some(timestamp);
+                }
+                if let Some(power_w) = total_power_active_import(&meter_values.readings) {
+                    session.peak_power_w = Some(
+                        session
+                            .peak_power_w
+                            .map_or(power_w, |peak| peak.max(power_w)),
+                    );
+
+                    let timestamp_s = timestamp.timestamp_millis() as f64 / 1000.0;
+                    match &mut session.integrated_energy {
+                        Some(accumulator) => accumulator.add(timestamp_s, power_w),
+                        None => {
+                            session.integrated_energy =
+                                Some(EnergyAccumulator::new(timestamp_s, power_w))
+                        }
+                    }
+                }
+            }
+            OcppMessage::StopTransaction(request) => {
+                let session = self.session(station_id, &request.transaction_id.to_string());
+                session.end_time = Some(timestamp);
+                session.stopped = true;
+                session.meter_stop_wh = Some(request.meter_stop);
+            }
+            _ => {}
+        }
+    }
+
+    fn rows(&self) -> Vec<SessionRow> {
+        self.sessions
+            .iter()
+            .map(|session| session.row(self.timezone))
+            .collect()
+    }
+
+    /// Cells of the rows of the table, under [`COLUMNS`].
+    pub fn cells(&self) -> Vec<[String; 8]> {
+        self.rows().iter().map(SessionRow::cells).collect()
+    }
+
+    /// Prints the sessions to stdout in the format of the report.
+    pub fn print(&self) -> anyhow::Result<()> {
+        match self.format {
+            ReportFormat::Table => self.print_table(),
+            ReportFormat::Csv => self.print_csv()?,
+            ReportFormat::Json => self.print_json()?,
+        }
+
+        Ok(())
+    }
+
+    fn print_table(&self) {
+        let mut table = Table::new();
+        table.set_header(COLUMNS);
+        for row in self.rows() {
+            table.add_row(row.cells());
+        }
+
+        println!("{}", table);
+    }
+
+    fn print_csv(&self) -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_writer(io::stdout());
+        writer.write_record(COLUMNS)?;
+        for row in self.rows() {
+            writer.write_record(row.cells())?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    fn print_json(&self) -> anyhow::Result<()> {
+        println!("{}", serde_json::to_string_pretty(&self.rows())?);
+
+        Ok(())
+    }
+}
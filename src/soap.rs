@@ -0,0 +1,280 @@
+//! Decoding of the SOAP envelopes of OCPP 1.5 and 1.6 over SOAP, whose body holds the same
+//! messages as the OCPP-J frames. The fields are converted to JSON with the types of the schema of
+//! their action, so that they decode into the same structs as the JSON payloads.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use quick_xml::Reader;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use serde_json::Value;
+
+use crate::parsing::OcppFrame;
+use crate::validation::BUNDLED_SCHEMAS;
+
+/// The bundled schemas, parsed once.
+static SCHEMAS: LazyLock<HashMap<&str, Value>> = LazyLock::new(|| {
+    BUNDLED_SCHEMAS
+        .iter()
+        .filter_map(|(name, schema)| Some((*name, serde_json::from_str(schema).ok()?)))
+        .collect()
+});
+
+/// Names of OCPP 1.5 elements that are called differently in OCPP 1.6, by parent element: the
+/// meter values are `values` with their sampled values as `value` elements.
+const OCPP_15_NAMES: [(&str, &str, &str); 2] = [
+    ("meterValuesRequest", "values", "meterValue"),
+    ("values", "value", "sampledValue"),
+];
+
+/// XML element, by the local names of it and its attributes.
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn new(start: &BytesStart) -> Option<Self> {
+        let mut attributes = Vec::new();
+        for attribute in start.attributes() {
+            let attribute = attribute.ok()?;
+            if attribute.key.as_namespace_binding().is_some() {
+                continue;
+            }
+            attributes.push((
+                String::from_utf8(attribute.key.local_name().as_ref().to_vec()).ok()?,
+                attribute.unescape_value().ok()?.into_owned(),
+            ));
+        }
+
+        Some(Self {
+            name: String::from_utf8(start.local_name().as_ref().to_vec()).ok()?,
+            attributes,
+            children: Vec::new(),
+            text: String::new(),
+        })
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// The descendant at the end of the path of element names.
+    fn find(&self, path: &[&str]) -> Option<&Element> {
+        path.iter()
+            .try_fold(self, |element, name| element.child(name))
+    }
+
+    fn text(&self) -> String {
+        self.text.trim().to_owned()
+    }
+
+    /// Converts the element to JSON with the types of `schema`. A leaf that the schema doesn't
+    /// say is an object is converted to a string, a boolean or a number.
+    fn to_json(&self, schema: Option<&Value>) -> Value {
+        if self.children.is_empty()
+            && self.attributes.is_empty()
+            && schema_type(schema) != Some("object")
+        {
+            return leaf(&self.text(), schema);
+        }
+
+        self.to_object(schema)
+    }
+
+    /// Converts the element to a JSON object with a field per attribute and child element. Child
+    /// elements that are repeated or that the schema says are arrays become arrays, and the text
+    /// becomes the `value` field, like the one of the sampled values of OCPP 1.5.
+    fn to_object(&self, schema: Option<&Value>) -> Value {
+        let property = |name: &str| schema.and_then(|schema| schema.get("properties")?.get(name));
+        let mut object = serde_json::Map::new();
+        for (name, value) in &self.attributes {
+            object.insert(name.clone(), leaf(value, property(name)));
+        }
+        if !self.text().is_empty() {
+            object.insert("value".to_owned(), leaf(&self.text(), property("value")));
+        }
+
+        for child in &self.children {
+            let name = OCPP_15_NAMES
+                .iter()
+                .find(|(parent, name, _)| *parent == self.name && *name == child.name)
+                .map_or(child.name.as_str(), |(_, _, name)| name);
+            let child_schema = property(name);
+            if schema_type(child_schema) == Some("array") {
+                let items = child_schema.and_then(|schema| schema.get("items"));
+                if let Value::Array(values) = object
+                    .entry(name)
+                    .or_insert_with(|| Value::Array(Vec::new()))
+                {
+                    values.push(child.to_json(items));
+                }
+                continue;
+            }
+
+            let value = child.to_json(child_schema);
+            match object.get_mut(name) {
+                Some(Value::Array(values)) => values.push(value),
+                Some(first) => *first = Value::Array(vec![first.take(), value]),
+                None => {
+                    object.insert(name.to_owned(), value);
+                }
+            }
+        }
+
+        Value::Object(object)
+    }
+}
+
+fn schema_type(schema: Option<&Value>) -> Option<&str> {
+    schema?.get("type")?.as_str()
+}
+
+/// Text with the type of `schema`, a string if it doesn't fit.
+fn leaf(text: &str, schema: Option<&Value>) -> Value {
+    let value = match schema_type(schema) {
+        Some("integer") => text.parse::<i64>().ok().map(Value::from),
+        Some("number") => text
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        Some("boolean") => match text {
+            "true" | "1" => Some(Value::Bool(true)),
+            "false" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    value.unwrap_or_else(|| Value::String(text.to_owned()))
+}
+
+/// Adds text to the innermost open element, text outside of the root element is whitespace.
+fn push_text(open: &mut [Element], text: &str) {
+    if let Some(element) = open.last_mut() {
+        element.text.push_str(text);
+    }
+}
+
+/// Parses the XML document into its root element.
+fn parse_xml(xml: &str) -> Option<Element> {
+    let mut reader = Reader::from_str(xml);
+    // Open elements, from the root to the innermost one.
+    let mut open: Vec<Element> = Vec::new();
+
+    loop {
+        let element = match reader.read_event().ok()? {
+            Event::Start(start) => {
+                open.push(Element::new(&start)?);
+                continue;
+            }
+            Event::Empty(start) => Element::new(&start)?,
+            Event::End(_) => open.pop()?,
+            Event::Text(text) => {
+                push_text(&mut open, &text.xml_content().ok()?);
+                continue;
+            }
+            Event::CData(data) => {
+                push_text(&mut open, &data.decode().ok()?);
+                continue;
+            }
+            Event::GeneralRef(reference) => {
+                let text = match reference.resolve_char_ref().ok()? {
+                    Some(character) => character.to_string(),
+                    None => resolve_predefined_entity(&reference.decode().ok()?)?.to_owned(),
+                };
+                push_text(&mut open, &text);
+                continue;
+            }
+            Event::Eof => return None,
+            Event::Decl(_) | Event::PI(_) | Event::Comment(_) | Event::DocType(_) => continue,
+        };
+
+        match open.last_mut() {
+            Some(parent) => parent.children.push(element),
+            None => return Some(element),
+        }
+    }
+}
+
+/// Whether the text is a SOAP envelope, which starts with an XML declaration or the `Envelope`
+/// element, e.g. `<s:Envelope`.
+pub fn is_soap(text: &str) -> bool {
+    let text = text.trim_start();
+
+    text.starts_with("<?xml")
+        || text
+            .strip_prefix('<')
+            .and_then(|text| text.split([' ', '>', '/']).next())
+            .is_some_and(|name| name.rsplit(':').next() == Some("Envelope"))
+}
+
+/// Action of the name of a body element, with whether it is a response: `BootNotification` and
+/// `false` for `bootNotificationRequest`.
+fn action(name: &str) -> Option<(String, bool)> {
+    let (action, response) = match name.strip_suffix("Response") {
+        Some(action) => (action, true),
+        None => (name.strip_suffix("Request")?, false),
+    };
+    let mut chars = action.chars();
+    let first = chars.next()?;
+
+    Some((first.to_uppercase().chain(chars).collect(), response))
+}
+
+/// Converts the SOAP envelope to the OCPP-J frame of its body. The WS-Addressing `MessageID` of a
+/// request and the `RelatesTo` of its response are their unique ID, or else their action, which
+/// pairs them as long as the calls of an action don't overlap.
+pub fn parse_soap_frame(xml: &str) -> Option<OcppFrame> {
+    let envelope = parse_xml(xml)?;
+    if envelope.name != "Envelope" {
+        return None;
+    }
+    let header = |name: &str| {
+        envelope
+            .find(&["Header", name])
+            .map(Element::text)
+            .filter(|text| !text.is_empty())
+    };
+    let message = envelope.child("Body")?.children.first()?;
+
+    if message.name == "Fault" {
+        // SOAP 1.2 faults, or else SOAP 1.1 ones.
+        let error_code = message
+            .find(&["Code", "Subcode", "Value"])
+            .or_else(|| message.find(&["Code", "Value"]))
+            .or_else(|| message.child("faultcode"))?
+            .text();
+        let error_description = message
+            .find(&["Reason", "Text"])
+            .or_else(|| message.child("faultstring"))
+            .map(Element::text)
+            .unwrap_or_default();
+
+        return Some(OcppFrame::CallError {
+            unique_id: header("RelatesTo").unwrap_or_default(),
+            // Without the namespace prefix of a qualified name, e.g. `s:Sender`.
+            error_code: error_code.rsplit(':').next().unwrap_or_default().to_owned(),
+            error_description,
+        });
+    }
+
+    let (action, response) = action(&message.name)?;
+    if response {
+        let schema = SCHEMAS.get(format!("{}Response", action).as_str());
+        Some(OcppFrame::CallResult {
+            unique_id: header("RelatesTo").unwrap_or(action),
+            payload: message.to_object(schema),
+        })
+    } else {
+        Some(OcppFrame::Call {
+            unique_id: header("MessageID").unwrap_or_else(|| action.clone()),
+            payload: message.to_object(SCHEMAS.get(action.as_str())),
+            action,
+        })
+    }
+}
@@ -0,0 +1,210 @@
+//! Line colors of the measurand series, from a built-in theme or a TOML file.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use rust_ocpp::v1_6::types::{Measurand, Phase};
+use serde::Deserialize;
+
+use log_viewer::ocpp::{MEASURANDS, PHASES, ocpp_name};
+
+/// Colors of the series of a theme file, applied on top of the default theme. `phases` colors all
+/// series of a phase, `measurands` the series of a measurand without a phase and `series` the
+/// series of a measurand on a phase, e.g. `[series."Current.Import"] L1 = [255, 0, 0]`. Names are
+/// the OCPP ones.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    phases: HashMap<String, [u8; 3]>,
+    measurands: HashMap<String, [u8; 3]>,
+    series: HashMap<String, HashMap<String, [u8; 3]>>,
+}
+
+/// Line color per measurand and phase, keyed by their OCPP names.
+#[derive(Clone)]
+pub struct ThemeMap {
+    colors: HashMap<(String, Option<String>), [u8; 3]>,
+}
+
+impl ThemeMap {
+    /// Loads one of the built-in themes, `default` or `phase-standard`, or a theme file.
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "default" => Ok(Self::default_theme()),
+            "phase-standard" => Ok(Self::phase_standard()),
+            path => Self::from_file(Path::new(path)),
+        }
+    }
+
+    /// Phased values are colored by phase so that L1/L2/L3 look the same across all measurands,
+    /// values without a phase get a color per measurand.
+    fn default_theme() -> Self {
+        let mut theme = Self {
+            colors: HashMap::new(),
+        };
+        for measurand in &MEASURANDS {
+            theme.set(measurand, None, default_measurand_color(measurand));
+            for phase in &PHASES {
+                theme.set(measurand, Some(phase), default_phase_color(phase));
+            }
+        }
+
+        theme
+    }
+
+    /// IEC 60445 colors of the line conductors: L1 brown, L2 black and L3 grey.
+    fn phase_standard() -> Self {
+        let mut theme = Self::default_theme();
+        for (phases, color) in [
+            ([Phase::L1, Phase::L1N], [139, 69, 19]),
+            ([Phase::L2, Phase::L2N], [0, 0, 0]),
+            ([Phase::L3, Phase::L3N], [128, 128, 128]),
+        ] {
+            for phase in &phases {
+                theme.set_phase(phase, color);
+            }
+        }
+
+        theme
+    }
+
+    fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let theme_file: ThemeFile = toml::from_str(
+            &fs::read_to_string(path)
+                .with_context(|| format!("Could not read theme `{}`", path.display()))?,
+        )
+        .with_context(|| format!("Could not parse theme `{}`", path.display()))?;
+
+        let mut theme = Self::default_theme();
+        for (phase, color) in &theme_file.phases {
+            theme.set_phase(&parse_name::<Phase>(phase)?, *color);
+        }
+        for (measurand, color) in &theme_file.measurands {
+            theme.set(&parse_name::<Measurand>(measurand)?, None, *color);
+        }
+        for (measurand, phases) in &theme_file.series {
+            let measurand = parse_name::<Measurand>(measurand)?;
+            for (phase, color) in phases {
+                theme.set(&measurand, Some(&parse_name::<Phase>(phase)?), *color);
+            }
+        }
+
+        Ok(theme)
+    }
+
+    fn set(&mut self, measurand: &Measurand, phase: Option<&Phase>, color: [u8; 3]) {
+        self.colors
+            .insert((ocpp_name(measurand), phase.map(ocpp_name)), color);
+    }
+
+    fn set_phase(&mut self, phase: &Phase, color: [u8; 3]) {
+        for measurand in &MEASURANDS {
+            self.set(measurand, Some(phase), color);
+        }
+    }
+
+    pub fn color(&self, measurand: &Measurand, phase: &Option<Phase>) -> [u8; 3] {
+        self.colors
+            .get(&(ocpp_name(measurand), phase.as_ref().map(ocpp_name)))
+            .copied()
+            .unwrap_or([128, 128, 128])
+    }
+}
+
+/// Parses the OCPP name of a measurand or phase, e.g. `Current.Import`.
+fn parse_name<T: serde::de::DeserializeOwned>(name: &str) -> anyhow::Result<T> {
+    serde_json::from_value(serde_json::Value::String(name.to_owned()))
+        .with_context(|| format!("Unknown measurand or phase `{}` in theme", name))
+}
+
+fn default_phase_color(phase: &Phase) -> [u8; 3] {
+    match phase {
+        Phase::L1 | Phase::L1N => [255, 0, 0],
+        Phase::L2 | Phase::L2N => [0, 200, 0],
+        Phase::L3 | Phase::L3N => [0, 100, 255],
+        Phase::N => [128, 128, 128],
+        Phase::L1L2 => [255, 160, 0],
+        Phase::L2L3 => [0, 200, 200],
+        Phase::L3L1 => [200, 0, 200],
+    }
+}
+
+fn default_measurand_color(measurand: &Measurand) -> [u8; 3] {
+    match measurand {
+        Measurand::CurrentExport | Measurand::PowerActiveExport => [255, 120, 0],
+        Measurand::CurrentImport | Measurand::PowerActiveImport => [255, 200, 0],
+        Measurand::CurrentOffered | Measurand::PowerOffered => [0, 160, 255],
+        Measurand::EnergyActiveExportRegister | Measurand::EnergyActiveExportInterval => {
+            [255, 80, 80]
+        }
+        Measurand::EnergyActiveImportRegister | Measurand::EnergyActiveImportInterval => {
+            [80, 200, 80]
+        }
+        Measurand::EnergyReactiveExportRegister | Measurand::EnergyReactiveExportInterval => {
+            [200, 80, 200]
+        }
+        Measurand::EnergyReactiveImportRegister | Measurand::EnergyReactiveImportInterval => {
+            [80, 160, 255]
+        }
+        Measurand::PowerReactiveExport => [200, 0, 120],
+        Measurand::PowerReactiveImport => [120, 0, 200],
+        Measurand::PowerFactor => [255, 255, 255],
+        Measurand::Frequency => [0, 220, 220],
+        Measurand::Rpm => [160, 160, 160],
+        Measurand::SoC => [0, 255, 100],
+        Measurand::Temperature => [255, 60, 0],
+        Measurand::Voltage => [255, 0, 255],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn load(theme: &str) -> anyhow::Result<ThemeMap> {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), theme).unwrap();
+
+        ThemeMap::from_file(file.path())
+    }
+
+    #[test]
+    fn series_override_phases() {
+        let theme = load(
+            "[phases]\nL1 = [1, 1, 1]\n\n[measurands]\nVoltage = [2, 2, 2]\n\n\
+             [series.\"Current.Import\"]\nL1 = [3, 3, 3]",
+        )
+        .unwrap();
+
+        assert_eq!(
+            theme.color(&Measurand::CurrentImport, &Some(Phase::L1)),
+            [3, 3, 3]
+        );
+        assert_eq!(
+            theme.color(&Measurand::Voltage, &Some(Phase::L1)),
+            [1, 1, 1]
+        );
+        assert_eq!(theme.color(&Measurand::Voltage, &None), [2, 2, 2]);
+        // The rest is the default theme.
+        assert_eq!(
+            theme.color(&Measurand::Voltage, &Some(Phase::L2)),
+            default_phase_color(&Phase::L2)
+        );
+    }
+
+    #[test]
+    fn unknown_names() {
+        for theme in [
+            "[phases]\nL4 = [1, 1, 1]",
+            "[measurands]\nCurrentImport = [1, 1, 1]",
+            "[series.\"Current.Import\"]\nL4 = [1, 1, 1]",
+            "[series.Bogus]\nL1 = [1, 1, 1]",
+            "[colors]\nL1 = [1, 1, 1]",
+        ] {
+            assert!(load(theme).is_err(), "`{}` was accepted", theme);
+        }
+    }
+}
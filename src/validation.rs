@@ -0,0 +1,117 @@
+//! Validation of OCPP 1.6 payloads against the JSON schemas of their actions, which covers the
+//! constraints the deserialization doesn't check, e.g. string lengths and required fields.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use jsonschema::Validator;
+
+macro_rules! bundled_schemas {
+    ($($name:literal),* $(,)?) => {
+        [$(($name, include_str!(concat!("../assets/schemas/", $name, ".json")))),*]
+    };
+}
+
+/// Schemas of the calls a charge point sends and their results, named like the files of the
+/// official schema distribution.
+pub(crate) const BUNDLED_SCHEMAS: [(&str, &str); 20] = bundled_schemas![
+    "Authorize",
+    "AuthorizeResponse",
+    "BootNotification",
+    "BootNotificationResponse",
+    "DataTransfer",
+    "DataTransferResponse",
+    "DiagnosticsStatusNotification",
+    "DiagnosticsStatusNotificationResponse",
+    "FirmwareStatusNotification",
+    "FirmwareStatusNotificationResponse",
+    "Heartbeat",
+    "HeartbeatResponse",
+    "MeterValues",
+    "MeterValuesResponse",
+    "StartTransaction",
+    "StartTransactionResponse",
+    "StatusNotification",
+    "StatusNotificationResponse",
+    "StopTransaction",
+    "StopTransactionResponse",
+];
+
+/// Validator per schema name, `<Action>` for calls and `<Action>Response` for their results.
+pub struct OcppValidator {
+    validators: HashMap<String, Validator>,
+}
+
+impl OcppValidator {
+    /// Uses the bundled schemas, or the `*.json` files of `directory`, e.g. the ones of the
+    /// official OCPP 1.6 distribution for the calls a central system sends as well.
+    pub fn load(directory: Option<&Path>) -> anyhow::Result<Self> {
+        let schemas = match directory {
+            Some(directory) => read_schemas(directory)?,
+            None => BUNDLED_SCHEMAS
+                .iter()
+                .map(|(name, schema)| (name.to_string(), schema.to_string()))
+                .collect(),
+        };
+
+        let validators = schemas
+            .into_iter()
+            .map(|(name, schema)| {
+                let schema = serde_json::from_str(&schema)
+                    .with_context(|| format!("Could not parse the schema of `{}`", name))?;
+                let validator = jsonschema::validator_for(&schema)
+                    .map_err(|e| anyhow::anyhow!("Invalid schema of `{}`: {}", name, e))?;
+                Ok((name, validator))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { validators })
+    }
+
+    /// Violations of the payload of a call of `action`, nothing if there's no schema for it.
+    pub fn validate_call(&self, action: &str, payload: &serde_json::Value) -> Vec<String> {
+        self.validate(action, payload)
+    }
+
+    /// Like [`OcppValidator::validate_call`], for the result of a call of `action`.
+    pub fn validate_call_result(&self, action: &str, payload: &serde_json::Value) -> Vec<String> {
+        self.validate(&format!("{}Response", action), payload)
+    }
+
+    fn validate(&self, name: &str, payload: &serde_json::Value) -> Vec<String> {
+        let Some(validator) = self.validators.get(name) else {
+            return Vec::new();
+        };
+
+        validator
+            .iter_errors(payload)
+            .map(|error| match error.instance_path.as_str() {
+                "" => error.to_string(),
+                path => format!("{}: {}", path, error),
+            })
+            .collect()
+    }
+}
+
+fn read_schemas(directory: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let mut schemas = Vec::new();
+    for entry in fs::read_dir(directory)
+        .with_context(|| format!("Could not read schema directory `{}`", directory.display()))?
+    {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        let schema = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read schema `{}`", path.display()))?;
+        schemas.push((name.to_owned(), schema));
+    }
+
+    Ok(schemas)
+}
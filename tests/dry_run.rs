@@ -0,0 +1,231 @@
+//! Runs the binary with `--dry-run` on the trace files in `tests/fixtures` and checks the parse
+//! summary it prints. Cargo builds the binary before running integration tests, so there's nothing
+//! to set up.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn dry_run(fixture: &str, args: &[&str]) -> Output {
+    dry_run_input("--trace-file", fixture, args)
+}
+
+/// Like [`dry_run`], with the fixture passed to `input` instead of `--trace-file`.
+fn dry_run_input(input: &str, fixture: &str, args: &[&str]) -> Output {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(fixture);
+
+    Command::new(env!("CARGO_BIN_EXE_log_viewer"))
+        .arg(input)
+        .arg(fixture)
+        .arg("--dry-run")
+        .args(args)
+        // Keep a config file of the user out of the tests.
+        .env("XDG_CONFIG_HOME", env!("CARGO_TARGET_TMPDIR"))
+        // Debug builds log debug messages by default.
+        .env("RUST_LOG", "warn")
+        .output()
+        .expect("Could not run log_viewer")
+}
+
+/// The number after `label` in the parse summary on stderr.
+fn summary_count(output: &Output, label: &str) -> usize {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr
+        .lines()
+        .find_map(|line| line.strip_prefix(label))
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or_else(|| panic!("No `{}` in the summary:\n{}", label, stderr))
+}
+
+#[test]
+fn valid_trace_file() {
+    let output = dry_run("valid.trace", &[]);
+
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Files read:"), 1);
+    assert_eq!(summary_count(&output, "Lines read:"), 12);
+    assert_eq!(summary_count(&output, "Skipped, too few columns:"), 0);
+    assert_eq!(summary_count(&output, "Skipped, bad timestamp:"), 0);
+    assert_eq!(summary_count(&output, "Invalid OCPP-J frames:"), 0);
+    assert_eq!(summary_count(&output, "Invalid payloads:"), 0);
+    assert_eq!(summary_count(&output, "Parsed as MeterValues:"), 2);
+    assert_eq!(summary_count(&output, "Parsed as other messages:"), 6);
+}
+
+#[test]
+fn malformed_trace_file() {
+    let output = dry_run("malformed.trace", &[]);
+
+    // The invalid frame and the invalid payload fail the run, the skipped lines don't.
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(summary_count(&output, "Lines read:"), 7);
+    assert_eq!(summary_count(&output, "Skipped, too few columns:"), 1);
+    assert_eq!(summary_count(&output, "Skipped, bad timestamp:"), 2);
+    assert_eq!(summary_count(&output, "Invalid OCPP-J frames:"), 1);
+    assert_eq!(summary_count(&output, "Unknown OCPP actions:"), 1);
+    assert_eq!(summary_count(&output, "Invalid payloads:"), 1);
+    assert_eq!(summary_count(&output, "Parsed as MeterValues:"), 1);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 trace lines failed to parse"));
+}
+
+#[test]
+fn empty_trace_file() {
+    let output = dry_run("empty.trace", &[]);
+
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Files read:"), 1);
+    assert_eq!(summary_count(&output, "Lines read:"), 0);
+    assert_eq!(summary_count(&output, "Parsed as MeterValues:"), 0);
+    assert_eq!(summary_count(&output, "Parsed as other messages:"), 0);
+}
+
+#[test]
+fn session_report() {
+    let output = dry_run("valid.trace", &["--json-report"]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(report.as_array().map(Vec::len), Some(1));
+    assert_eq!(report[0]["transaction_id"], "7");
+    assert_eq!(report[0]["connector_id"], 1);
+    assert_eq!(report[0]["duration_min"], 4.0);
+    // From meterStart and meterStop, not from the integrated power.
+    assert_eq!(report[0]["energy_kwh"], 0.31);
+    assert_eq!(report[0]["peak_power_kw"], 3.68);
+}
+
+#[test]
+fn overlapping_session_starts() {
+    let output = dry_run("overlapping_starts.trace", &["--json-report"]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    // The results come in the other order, and connector 2 retried a start whose result was
+    // lost.
+    assert!(output.status.success());
+    assert_eq!(report.as_array().map(Vec::len), Some(2));
+    assert_eq!(report[0]["transaction_id"], "20");
+    assert_eq!(report[0]["connector_id"], 2);
+    assert_eq!(report[0]["start_time"], "2024-01-15T08:01:40+00:00");
+    assert_eq!(report[0]["energy_kwh"], 0.2);
+    assert_eq!(report[1]["transaction_id"], "10");
+    assert_eq!(report[1]["connector_id"], 1);
+    assert_eq!(report[1]["start_time"], "2024-01-15T08:01:30+00:00");
+    assert_eq!(report[1]["energy_kwh"], 0.5);
+}
+
+#[test]
+fn crlf_trace_file() {
+    let output = dry_run("crlf.trace", &[]);
+
+    // Same lines as `valid.trace`, with Windows line endings.
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Lines read:"), 12);
+    assert_eq!(summary_count(&output, "Skipped, bad timestamp:"), 0);
+    assert_eq!(summary_count(&output, "Invalid OCPP-J frames:"), 0);
+    assert_eq!(summary_count(&output, "Parsed as MeterValues:"), 2);
+    assert_eq!(summary_count(&output, "Parsed as other messages:"), 6);
+}
+
+#[test]
+fn nine_columns_trace_file() {
+    let output = dry_run("nine_columns.trace", &[]);
+
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Lines read:"), 4);
+    assert_eq!(summary_count(&output, "Skipped, too few columns:"), 0);
+    assert_eq!(summary_count(&output, "Parsed as MeterValues:"), 1);
+    assert_eq!(summary_count(&output, "Parsed as other messages:"), 1);
+
+    // Only with the default layout.
+    let output = dry_run("nine_columns.trace", &["--min-cols", "10"]);
+
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Skipped, too few columns:"), 4);
+}
+
+#[test]
+fn glob_characters_in_extension() {
+    let output = dry_run_input("--trace-file-directory", "", &["--extension", "["]);
+
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Files read:"), 0);
+}
+
+#[test]
+fn unreadable_trace_file() {
+    let output = dry_run("invalid_utf8.trace", &[]);
+
+    // Logged and counted instead of aborting the run.
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Files read:"), 0);
+    assert_eq!(summary_count(&output, "Files failed to read:"), 1);
+    assert_eq!(summary_count(&output, "Lines read:"), 0);
+}
+
+#[test]
+fn too_large_trace_file() {
+    let output = dry_run("valid.trace", &["--max-file-size-mb", "0"]);
+
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Files read:"), 0);
+    assert_eq!(summary_count(&output, "Files failed to read:"), 1);
+    assert_eq!(summary_count(&output, "Lines read:"), 0);
+}
+
+#[test]
+fn max_lines() {
+    let output = dry_run("valid.trace", &["--max-lines", "3"]);
+
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Parsed as MeterValues:"), 0);
+    assert_eq!(summary_count(&output, "Parsed as other messages:"), 3);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the output is incomplete"));
+}
+
+#[test]
+fn quiet_dry_run() {
+    let output = dry_run("valid.trace", &["--quiet"]);
+
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+
+    let output = dry_run("malformed.trace", &["--quiet"]);
+
+    // Only the error the run fails with.
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).starts_with("Error: 2 trace lines failed to parse")
+    );
+}
+
+#[test]
+fn no_summary_dry_run() {
+    let output = dry_run("valid.trace", &["--no-summary"]);
+
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn pcap_capture() {
+    let output = dry_run_input("--pcap", "websocket.pcap", &["--json-report"]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    // The MeterValues frame is split over two segments that arrive out of order, the
+    // StatusNotification over two frames.
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Files read:"), 1);
+    assert_eq!(summary_count(&output, "Lines read:"), 6);
+    assert_eq!(summary_count(&output, "Invalid OCPP-J frames:"), 0);
+    assert_eq!(summary_count(&output, "Parsed as MeterValues:"), 1);
+    assert_eq!(summary_count(&output, "Parsed as other messages:"), 2);
+    // From the URL path, and the capture time of the packet that completed the frame.
+    assert_eq!(report[0]["station_id"], "EVSE-001");
+    assert_eq!(report[0]["start_time"], "2024-01-15T08:02:00.010+00:00");
+
+    let output = dry_run_input("--pcap", "websocket.pcap", &["--pcap-port", "9000"]);
+
+    assert!(output.status.success());
+    assert_eq!(summary_count(&output, "Lines read:"), 0);
+}